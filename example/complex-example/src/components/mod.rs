@@ -0,0 +1,3 @@
+pub mod pagination;
+
+pub use pagination::*;
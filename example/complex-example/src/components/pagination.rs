@@ -0,0 +1,60 @@
+use maud::html;
+use paro_rs::{ComponentScope, ParoComponent};
+
+
+/**
+ * A reusable pâro component. It knows nothing about the application it is
+ * used in, it just tells its parent when the page changed.
+ */
+pub struct Pagination {
+    pub current_page: usize,
+    pub page_count: usize,
+}
+
+pub enum PaginationMessage {
+    PageChanged(usize),
+}
+
+impl Pagination {
+    pub fn default() -> Pagination {
+        Pagination {
+            current_page: 0,
+            page_count: 0,
+        }
+    }
+}
+
+impl ParoComponent for Pagination {
+    type Message = PaginationMessage;
+
+    fn render<State: Send + Sync + 'static>(&self, scope: &ComponentScope<State, Self>) -> String {
+        html! {
+            div {
+                "Page: " ((self.current_page + 1))
+                "/" (self.page_count)
+
+                @if self.current_page != 0 {
+                    button.btn."btn-primary" type="button" onclick=({
+                        scope.event(|pagination: &mut Pagination, _: Option<String>, emitter| {
+                            pagination.current_page -= 1;
+                            emitter.emit(PaginationMessage::PageChanged(pagination.current_page));
+                        })
+                    }) {
+                        "prev page"
+                    }
+                }
+
+                @if self.page_count > 0 && self.current_page != (self.page_count - 1) {
+                    button.btn."btn-primary" type="button" onclick=({
+                        scope.event(|pagination: &mut Pagination, _: Option<String>, emitter| {
+                            pagination.current_page += 1;
+                            emitter.emit(PaginationMessage::PageChanged(pagination.current_page));
+                        })
+                    }) {
+                        "next page"
+                    }
+                }
+            }
+        }.into_string()
+    }
+}
@@ -79,6 +79,7 @@ pub fn generate_employees(count: usize) -> Vec<Arc<Employee>> {
             last_name: last_name.to_owned(),
             login,
             department,
+            manager: None,
         };
         employees.push(Arc::new(employee));
     }
@@ -9,14 +9,15 @@ use std::net::SocketAddr;
 use tauri::*;
 use tokio::net::{TcpListener, TcpStream};
 use futures_util::{SinkExt, StreamExt};
-use tokio_tungstenite::{accept_async};
+use tokio_tungstenite::{accept_async, WebSocketStream};
 use tungstenite::{Result, Message};
 
-use paro_rs::{ParoApp};
+use paro_rs::{ParoApp, ParoMessage, ParoReceiver, render_update};
 
 mod state;
 mod router;
 mod pages;
+mod components;
 mod data_generation;
 use state::*;
 use router::*;
@@ -41,30 +42,50 @@ async fn start_server() {
  * Accept a connection and forward to handle_connection
  */
 async fn accept_connection(paro_app: Arc<RwLock<ParoApp<ApplicationState>>>, peer: SocketAddr, stream: TcpStream) {
-    if let Err(e) = handle_connection(paro_app, peer, stream).await {
+    let ws_stream = accept_async(stream).await.expect("Failed to accept");
+
+    println!("New WebSocket connection: {}", peer);
+
+    // messages pâro sends on its own, like queries to paro.js
+    let (outgoing, connection) = paro_app.write().unwrap().connect();
+
+    if let Err(e) = handle_connection(paro_app.clone(), ws_stream, outgoing).await {
         match e {
             err => println!("Error processing connection: {}", err),
         }
     }
+    // drops callbacks still waiting for the user, like an unanswered confirm,
+    // unless a newer connection took over already
+    paro_app.write().unwrap().disconnect(connection);
 }
 
 /**
  * This is where we do the server side work for your application
  */
-async fn handle_connection(paro_app: Arc<RwLock<ParoApp<ApplicationState>>>, peer: SocketAddr, stream: TcpStream) -> Result<()> {
-    let mut ws_stream = accept_async(stream).await.expect("Failed to accept");
-
-    println!("New WebSocket connection: {}", peer);
-
-    // initial html
-    let rendered_html = render_page(&mut paro_app.clone());
+async fn handle_connection(paro_app: Arc<RwLock<ParoApp<ApplicationState>>>, mut ws_stream: WebSocketStream<TcpStream>, mut outgoing: ParoReceiver) -> Result<()> {
+    // initial html, via render_update so pâro knows which callbacks are on the page
+    let rendered_html = render_update(&mut paro_app.clone(), render_page);
     ws_stream.send(Message::Text(rendered_html)).await?;
-    
+
     // You can have an eventloop here to match pâro message input, database returns result,
     // async api calls, etc
 
-    while let Some(msg) = ws_stream.next().await {
-        let msg = msg?;
+    loop {
+        let msg = tokio::select! {
+            msg = ws_stream.next() => match msg {
+                Some(msg) => msg?,
+                None => break,
+            },
+            Some(message) = outgoing.next() => {
+                ws_stream.send(match message {
+                    ParoMessage::Text(text) => Message::Text(text),
+                    ParoMessage::Binary(bytes) => Message::Binary(bytes),
+                    // an async callback continued on its own, like after a timer
+                    ParoMessage::Render => Message::Text(render_update(&mut paro_app.clone(), render_page)),
+                }).await?;
+                continue;
+            },
+        };
         println!("got message from pâro");
         if msg.is_text() || msg.is_binary() {
             if msg.is_text() && msg.to_text().unwrap().eq("ping") {
@@ -77,8 +98,10 @@ async fn handle_connection(paro_app: Arc<RwLock<ParoApp<ApplicationState>>>, pee
                 loop {
                     match paro_app.try_write() {
                         Ok(ref mut data) => {
-                            data.call(event_id.to_owned())
-                                .expect(&format!("could not call paro callback for id '{}'", event_id));
+                            // for example a second click on a save button, whose callback is one-shot
+                            if let Err(error) = data.call(event_id.to_owned()) {
+                                println!("could not call paro callback: {}", error);
+                            }
         
                             // clean up old callbacks to free memory
                             data.iterate();
@@ -90,8 +113,9 @@ async fn handle_connection(paro_app: Arc<RwLock<ParoApp<ApplicationState>>>, pee
                         },
                     }
                 }
-                // render updated html and fill callbackstore with current callbacks
-                let rendered_html = render_page(&mut paro_app.clone());
+                // render updated html (only the regions the callback marked dirty, if any)
+                // and fill callbackstore with current callbacks
+                let rendered_html = render_update(&mut paro_app.clone(), render_page);
                 // send updated html to the client, so it can be shown to the user
                 ws_stream.send(Message::Text(rendered_html)).await?;
             }
@@ -22,6 +22,7 @@ impl AddState {
                 last_name: "".to_owned(),
                 login: "".to_owned(),
                 department: Department::Production,
+                manager: None,
             },
             validation: EmployeeValidation::default(),
         }
@@ -119,15 +120,18 @@ pub fn render_add(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) -> Stri
         add_state.validation.validate(&add_state.employee, &state.employees, false);
     }));
 
-    let on_submit = event!(paro_app, (move |state: &mut ApplicationState, _value: Option<String>| {
+    let on_submit = event_once!(paro_app, (move |state: &mut ApplicationState, _value: Option<String>| {
         let mut add_state = &mut state.add_state;
         if add_state.validation.is_valid() {
             state.employees.push(Arc::new(add_state.employee.clone()));
+            let toast = ParoAction::Toast(format!("{} was added", add_state.employee.login));
             add_state.employee = Employee::default();
             add_state.validation.validate(&add_state.employee, &state.employees, false);
             state.list_state.filter_employees(&state.employees);
             state.page = Page::List;
+            return toast;
         }
+        ParoAction::Render
     }));
 
     let add_state = &paro_app.read().unwrap().state.add_state;
@@ -148,7 +152,7 @@ pub fn render_add(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) -> Stri
             ))
 
             div."col-12" {
-                button.btn."btn-primary" type="submit" onclick=({on_submit}) {
+                button.btn."btn-primary" type="submit" data-paro-disable-while-pending onclick=({on_submit}) {
                     "Add to list"
                 }
             }
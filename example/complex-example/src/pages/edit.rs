@@ -1,7 +1,7 @@
 
 use std::sync::{Arc, RwLock};
 
-use maud::html;
+use maud::{html, PreEscaped};
 
 use paro_rs::*;
 
@@ -12,6 +12,8 @@ use crate::router::*;
 pub struct EditState {
     pub employee: Option<Employee>,
     validation: EmployeeValidation,
+    // picks one of the 1000 employees, too many for a select
+    pub manager: Combobox<String>,
 }
 
 impl EditState {
@@ -19,8 +21,28 @@ impl EditState {
         EditState {
             employee: None,
             validation: EmployeeValidation::default(),
+            manager: Combobox::new("manager", "Manager").placeholder("Search by name or login"),
         }
     }
+
+    /**
+     * Starts editing another employee, with its manager shown in the combobox.
+     * Only done here, so renders keep what the user typed into the combobox.
+     */
+    pub fn edit(&mut self, employee: Option<Employee>, employees: &[Arc<Employee>]) {
+        match employee.as_ref()
+            .and_then(|employee| employee.manager.as_ref())
+            .and_then(|id| employees.iter().find(|manager| &manager.id == id)) {
+            Some(manager) => self.manager.select(&manager_label(manager), manager.id.clone()),
+            None => self.manager.clear(),
+        }
+        self.employee = employee;
+    }
+}
+
+
+fn manager_label(employee: &Employee) -> String {
+    format!("{} {} ({})", employee.first_name, employee.last_name, employee.login)
 }
 
 
@@ -60,15 +82,8 @@ pub fn render_edit(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) -> Str
         }
     }));
 
-    let on_submit = event!(paro_app, (move |state: &mut ApplicationState, _value: Option<String>| {
-        let edit_state = &mut state.edit_state;
-        if edit_state.validation.is_valid() {
-            if let Some(employee) = edit_state.employee.take() {
-                state.employees.push(Arc::new(employee.clone()));
-                state.list_state.filter_employees(&state.employees);
-                state.page = Page::List;
-            }
-        }
+    let on_submit = event_once!(paro_app, (move |state: &mut ApplicationState, _value: Option<String>, ctx: &mut ParoContext<ApplicationState>| {
+        save(state, ctx);
     }));
 
     let content = html! {
@@ -76,6 +91,13 @@ pub fn render_edit(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) -> Str
             "Edit member"
         }
         
+        // press ? to see them
+        (PreEscaped(hotkey(paro_app, Hotkey::new("ctrl+s", "Save changes", save))))
+        (PreEscaped(hotkey(paro_app, Hotkey::new("Escape", "Discard changes",
+            |state: &mut ApplicationState, _ctx: &mut ParoContext<ApplicationState>| {
+                state.edit_state.employee = None;
+                state.page = Page::List;
+            }))))
         form.row."g-3"."was-validated" {
 
             @if let Some(employee) = edit_state.employee.as_ref() {
@@ -88,8 +110,37 @@ pub fn render_edit(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) -> Str
                 ))
             }
 
+            div."col-md-6" {
+                label."form-label" for=(edit_state.manager.input_id()) {
+                    "Manager"
+                }
+                (PreEscaped(render_component(
+                    paro_app,
+                    &edit_state.manager,
+                    |state: &mut ApplicationState| &mut state.edit_state.manager,
+                    |state: &mut ApplicationState, message: ComboboxMessage<String>| match message {
+                        ComboboxMessage::Search(text) => {
+                            let mut suggestions: Vec<(i64, Suggestion<String>)> = state.employees.iter()
+                                .filter_map(|manager| {
+                                    let label = manager_label(manager);
+                                    fuzzy_score(&text, &label).map(|score| (score, Suggestion::new(&label, manager.id.clone())))
+                                })
+                                .collect();
+                            suggestions.sort_by_key(|(score, _)| -score);
+                            state.edit_state.manager.set_suggestions(suggestions.into_iter().map(|(_, suggestion)| suggestion).collect());
+                        },
+                        ComboboxMessage::Selected(id) => if let Some(employee) = state.edit_state.employee.as_mut() {
+                            employee.manager = Some(id);
+                        },
+                        ComboboxMessage::Cleared => if let Some(employee) = state.edit_state.employee.as_mut() {
+                            employee.manager = None;
+                        },
+                    },
+                )))
+            }
+
             div."col-12" {
-                button.btn."btn-primary" type="submit" onclick=({on_submit}) {
+                button.btn."btn-primary" type="submit" data-paro-disable-while-pending onclick=({on_submit}) {
                     "Save Changes"
                 }
             }
@@ -101,3 +152,19 @@ pub fn render_edit(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) -> Str
     markup
 }
 
+
+fn save(state: &mut ApplicationState, ctx: &mut ParoContext<ApplicationState>) {
+    let edit_state = &mut state.edit_state;
+    if edit_state.validation.is_valid() {
+        if let Some(employee) = edit_state.employee.take() {
+            state.employees.push(Arc::new(employee.clone()));
+            state.list_state.filter_employees(&state.employees);
+            state.page = Page::List;
+            // show the user where the employee ended up
+            ctx.command(ParoCommand::scroll_into_view(&format!("[data-employee-id='{}']", employee.id)));
+            ctx.command(ParoCommand::focus("#search"));
+            ctx.toast(Level::Success, &format!("Saved {} {}", employee.first_name, employee.last_name));
+        }
+    }
+}
+
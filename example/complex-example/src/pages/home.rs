@@ -3,18 +3,43 @@ use std::sync::{Arc, RwLock};
 
 use maud::html;
 
-use paro_rs::ParoApp;
+use paro_rs::*;
 
 use crate::state::ApplicationState;
 use crate::pages::render_layout;
 
 
 pub fn render_home(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) -> String {
+    // generated on every render, but only send to paro.js when the numbers changed. Named, so
+    // a new chart replaces the previous one instead of keeping every chart ever rendered
+    let chart = {
+        let paro_app = paro_app.read().unwrap();
+        paro_app.canvas("sketch").draw(|frame| paro_app.state.draw_sketch(frame));
+        paro_app.images().register_named("department-chart", "image/svg+xml", paro_app.state.department_chart().into_bytes())
+    };
     let content = html! {
         h1 {
             "Overview"
         }
-        
+        img alt="employees per department" src=(chart.src()) {}
+        h2 {
+            "Sketch"
+        }
+        // drawn via ParoCanvas, moving the pointer does not render any html
+        canvas data-paro-canvas="sketch" width="400" height="150" style="border: 1px solid #ccc; touch-action: none;"
+            onpointerdown=(event!(paro_app, |state: &mut ApplicationState, pointer: CanvasPointer, ctx: &mut ParoContext<ApplicationState>| {
+                state.sketch.push(vec![(pointer.x, pointer.y)]);
+                ctx.canvas("sketch").draw(|frame| state.draw_sketch(frame));
+                ParoAction::NoRender
+            }, canvas))
+            // only while drawing and at most about 60 times a second, not for every move of the mouse
+            onpointermove=(event!(paro_app, |state: &mut ApplicationState, pointer: CanvasPointer, ctx: &mut ParoContext<ApplicationState>| {
+                if let Some(line) = state.sketch.last_mut() {
+                    line.push((pointer.x, pointer.y));
+                }
+                ctx.canvas("sketch").draw(|frame| state.draw_sketch(frame));
+                ParoAction::NoRender
+            }, canvas, pressed, throttle = "16")) {}
     };
     let markup = render_layout(&mut paro_app.clone(), content);
     markup
@@ -1,18 +1,82 @@
 
 use std::sync::{Arc, RwLock};
 
-use maud::{html, Markup};
-use paro_rs::ParoApp;
+use maud::{html, Markup, PreEscaped};
+use paro_rs::*;
 
 use crate::state::ApplicationState;
 use crate::pages::render_navigation;
+use crate::router::Page;
+
+
+/**
+ * Where the command palette can take you
+ */
+#[derive(Debug, Clone)]
+pub enum Jump {
+    Page(Page),
+    Employee(String),
+}
 
 
 pub fn render_layout(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>, content: Markup) -> String {
+    // everything the navigation shows. It is only rendered again if one of these changed.
+    let navigation_inputs = {
+        let state = &paro_app.read().unwrap().state;
+        (
+            state.page.clone(),
+            state.edit_state.employee.as_ref().map(|employee| employee.login.clone()),
+            state.list_state.search_term.clone(),
+        )
+    };
+    let navigation = memo(&mut paro_app.clone(), "navigation", &navigation_inputs, |paro_app| {
+        render_navigation(paro_app).into_string()
+    });
+
+    let palette = {
+        let app = paro_app.read().unwrap();
+        render_component(
+            paro_app,
+            &app.state.palette,
+            |state: &mut ApplicationState| &mut state.palette,
+            |state: &mut ApplicationState, message: PaletteMessage<Jump>| match message {
+                PaletteMessage::Search(query) => {
+                    let results = search(state, &query);
+                    state.palette.set_results(results);
+                },
+                PaletteMessage::Chosen(Jump::Page(page)) => state.page = page,
+                PaletteMessage::Chosen(Jump::Employee(id)) => {
+                    let employee = state.employees.iter()
+                        .find(|employee| employee.id == id)
+                        .map(|employee| (**employee).clone());
+                    state.edit_state.edit(employee, &state.employees);
+                    state.page = Page::Edit;
+                },
+            },
+        )
+    };
+
     let rendered_navbar = html! {
-        (render_navigation(&mut paro_app.clone()))
+        (PreEscaped(navigation))
+        (PreEscaped(palette))
 
         (content)
      }.into_string();
      rendered_navbar
 }
+
+
+fn search(state: &ApplicationState, query: &str) -> Vec<PaletteResult<Jump>> {
+    let pages = [("Home", Page::Home), ("Our Team", Page::List), ("Add member", Page::Add)];
+    let pages = pages.into_iter()
+        .filter_map(|(title, page)| fuzzy_score(query, title)
+            // pages first
+            .map(|score| PaletteResult::new(title, Jump::Page(page)).detail("page").score(score + 1000)));
+    let employees = state.employees.iter()
+        .filter_map(|employee| {
+            let name = format!("{} {}", employee.first_name, employee.last_name);
+            let score = fuzzy_score(query, &name).max(fuzzy_score(query, &employee.login))?;
+            Some(PaletteResult::new(&name, Jump::Employee(employee.id.clone())).detail(&employee.login).score(score))
+        });
+    pages.chain(employees).collect()
+}
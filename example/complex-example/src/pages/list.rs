@@ -1,13 +1,14 @@
 
 use std::sync::{Arc, RwLock};
 
-use maud::html;
+use maud::{html, PreEscaped};
 
 use paro_rs::*;
 
 use crate::state::*;
 use crate::pages::*;
 use crate::router::*;
+use crate::components::*;
 
 
 pub struct ListState {
@@ -17,8 +18,7 @@ pub struct ListState {
     pub sort_direction: Direction,
     pub sort_by: EmployeeField,
 
-    pub current_page: usize,
-    pub page_count: usize,
+    pub pagination: Pagination,
 }
 
 impl ListState {
@@ -30,26 +30,14 @@ impl ListState {
             sort_direction: Direction::Asc,
             sort_by: EmployeeField::Login,
 
-            current_page: 0,
-            page_count: 0,
-
+            pagination: Pagination::default(),
         };
         result.filter_employees(employees);
         result
     }
 
-    pub fn next_page(&mut self, employees: &Vec<Arc<Employee>>) -> () {
-        self.current_page += 1;
-        self.filter_employees(employees);
-    }
-
-    pub fn prev_page(&mut self, employees: &Vec<Arc<Employee>>) -> () {
-        self.current_page -= 1;
-        self.filter_employees(employees);
-    }
-
     pub fn sort_by(&mut self, field: EmployeeField, employees: &Vec<Arc<Employee>>) {
-        self.current_page = 0;
+        self.pagination.current_page = 0;
         if self.sort_by == field {
             if self.sort_direction == Direction::Asc {
                 self.sort_direction = Direction::Dsc;
@@ -90,9 +78,9 @@ impl ListState {
             filtered_employees.reverse();
         }
 
-        self.page_count = ((filtered_employees.len() as f32) / (25 as f32)).ceil() as usize;
+        self.pagination.page_count = ((filtered_employees.len() as f32) / (25 as f32)).ceil() as usize;
         let employees_to_show: Vec<Arc<Employee>> = filtered_employees.iter()
-            .skip(self.current_page * 25)
+            .skip(self.pagination.current_page * 25)
             .take(25)
             .map(|employee| employee.clone())
             .collect();
@@ -102,47 +90,66 @@ impl ListState {
 
 
 pub fn render_list(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) -> String {
-    let state = &paro_app.read().unwrap().state;
-    let list_state = &state.list_state;
-
-    let create_edit_event = |employee: Arc<Employee>| {
-        event!(paro_app, (move |state: &mut ApplicationState, _value: Option<String>| {
-            let mut edit_state = &mut state.edit_state;
-            let employee_clone: Employee = (*employee).clone();
-            edit_state.employee = Some(employee_clone);
-            state.page = Page::Edit;
-        }))
-    };
-
     let content = html! {
         h1 {
             "Our Team"
         }
-        div {
-            "Page: " ((list_state.current_page + 1))
-            "/" (list_state.page_count)
-            
-
-            @if list_state.current_page != 0 {
-                button.btn."btn-primary" type="button" onclick=({
-                    event!(paro_app, (move |state: &mut ApplicationState, _value: Option<String>| {
-                        state.list_state.prev_page(&state.employees);
-                    }))
-                }) {
-                    "prev page"
-                }
+        // csv files can be selected, dropped here from the file manager or pasted
+        div."mb-3".import data-paro-file-drop=(file_drop(paro_app, csv_import())) {
+            label."form-label" for="import" { "Import employees from a csv file (first name, last name, login, department)" }
+            input #import."form-control" type="file" accept=".csv,text/csv" onchange=(upload(paro_app, csv_import())) {}
+        }
+        div."mb-3" {
+            button.btn."btn-secondary" onclick=(event!(paro_app, |state: &mut ApplicationState, _, ctx: &mut ParoContext<ApplicationState>| {
+                ctx.command(ParoCommand::download("employees.csv", "text/csv", state.export_csv().as_bytes()));
+                ParoAction::NoRender
+            })) {
+                "Export employees as csv"
             }
-
-            @if list_state.page_count > 0 && list_state.current_page != (list_state.page_count - 1) {
-                button.btn."btn-primary" type="button" onclick=({
-                    event!(paro_app, (move |state: &mut ApplicationState, _value: Option<String>| {
-                        state.list_state.next_page(&state.employees);
-                    }))
-                }) {
-                    "next page"
+        }
+        div."mb-3"."d-flex"."gap-2" {
+            @for department in Department::into_iter() {
+                // drop an employee here to move them to the department
+                div."border"."rounded"."p-2".department data-paro-drop-target=(format!("{:?}", department))
+                    data-paro-drop=(on_drop(paro_app, &[DropPosition::Inside],
+                        |state: &mut ApplicationState, drop: ParoDrop<String>, _ctx: &mut ParoContext<ApplicationState>| {
+                            state.move_to_department(&drop.payload, &drop.target);
+                        })) {
+                    (department)
                 }
             }
         }
+        // the search field in the navigation only re-renders this region
+        div data-paro-region="results" {
+            (PreEscaped(render_region(&mut paro_app.clone(), "results", render_results)))
+        }
+    };
+    let markup = render_layout(&mut paro_app.clone(), content);
+    markup
+}
+
+
+fn csv_import() -> ParoUpload<ApplicationState> {
+    ParoUpload::new(|state: &mut ApplicationState, file: ParoFile, ctx: &mut ParoContext<ApplicationState>| {
+        let imported = state.import_csv(&String::from_utf8_lossy(&file.bytes));
+        ctx.apply(ParoAction::Toast(format!("imported {} employees from {}", imported, file.name)));
+    }).max_size(1024 * 1024).accept(&[".csv", "text/csv"])
+}
+
+
+fn render_results(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) -> String {
+    let state = &paro_app.read().unwrap().state;
+    let list_state = &state.list_state;
+
+    html! {
+        (PreEscaped(render_component(
+            paro_app,
+            &list_state.pagination,
+            |state: &mut ApplicationState| &mut state.list_state.pagination,
+            |state: &mut ApplicationState, message: PaginationMessage| match message {
+                PaginationMessage::PageChanged(_) => state.list_state.filter_employees(&state.employees),
+            },
+        )))
         table.table {
             thead {
                 tr {
@@ -177,32 +184,69 @@ pub fn render_list(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) -> Str
             }
             tbody {
                 @for employee in &list_state.filtered_employees {
-                    @let on_edit = create_edit_event(employee.clone());
-
-                    tr {
-                        td {
-                            (employee.first_name)
-                        }
-                        td {
-                            (employee.last_name)
-                        }
-                        td {
-                            (employee.login)
-                        }
-                        td {
-                            (employee.department)
-                        }
-                        td {
-                            button.btn."btn-primary" onclick=({on_edit}) {
-                                "Edit"
-                            }
-                        }
-                    }
+                    // rows of employees that did not change are not rendered again
+                    (PreEscaped(memo(
+                        &mut paro_app.clone(),
+                        &format!("employee-row-{}", employee.id),
+                        &**employee,
+                        |paro_app| render_employee_row(paro_app, employee.clone()),
+                    )))
                 }
             }
         }
-    
+    }.into_string()
+}
+
+
+fn render_employee_row(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>, employee: Arc<Employee>) -> String {
+    let on_edit = {
+        let employee = employee.clone();
+        event!(paro_app, (move |state: &mut ApplicationState, _value: Option<String>| {
+            let employee_clone: Employee = (*employee).clone();
+            state.edit_state.edit(Some(employee_clone), &state.employees);
+            state.page = Page::Edit;
+        }))
     };
-    let markup = render_layout(&mut paro_app.clone(), content);
-    markup
+
+    let on_delete = {
+        let employee = employee.clone();
+        event_async!(paro_app, (move |ctx: ParoAsyncContext<ApplicationState>, _value: Option<String>| {
+            let employee = employee.clone();
+            async move {
+                let question = format!("Delete {} {}?", employee.first_name, employee.last_name);
+                if ctx.confirm(&question).await {
+                    ctx.update(move |state, _ctx| {
+                        state.employees.retain(|other| other.id != employee.id);
+                        state.list_state.filter_employees(&state.employees);
+                    }).await;
+                }
+            }
+        }))
+    };
+
+    html! {
+        tr data-employee-id=(employee.id) draggable="true" data-paro-drag=(employee.id) {
+            td {
+                (employee.first_name)
+            }
+            td {
+                (employee.last_name)
+            }
+            td {
+                (employee.login)
+            }
+            td {
+                (employee.department)
+            }
+            td {
+                button.btn."btn-primary" onclick=({on_edit}) {
+                    "Edit"
+                }
+                " "
+                button.btn."btn-outline-danger" onclick=({on_delete}) {
+                    "Delete"
+                }
+            }
+        }
+    }.into_string()
 }
@@ -1,7 +1,7 @@
 use std::sync::{ Arc, RwLock };
 
 use maud::{ html, Markup };
-use paro_rs::{ ParoApp, event };
+use paro_rs::{ ParoApp, ParoContext, event };
 
 use crate::state::ApplicationState;
 use crate::router::Page;
@@ -72,13 +72,17 @@ pub fn render_navigation(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>)
               }
      
                 form."form-inline"."my-2"."my-lg-0" onsubmit=({
-                    event!(paro_app, (move |state: &mut ApplicationState, _| state.page = Page::List))
+                    event!(paro_app, (move |state: &mut ApplicationState, _| state.page = Page::List), prevent)
                 }) {
-                    input."form-control"."mr-sm-2" type="search" placeholder="Search" aria-label="Search" oninput=({
-                        event!(paro_app, (move |state: &mut ApplicationState, value: Option<String>| {
+                    input #search."form-control"."mr-sm-2" type="search" placeholder="Search" aria-label="Search" oninput=({
+                        event!(paro_app, (move |state: &mut ApplicationState, value: Option<String>, ctx: &mut ParoContext<ApplicationState>| {
+                            // the search field is not part of the results, so it keeps focus and caret position
+                            if state.page == Page::List {
+                                ctx.mark_dirty("results");
+                            }
                             state.page = Page::List;
                             state.list_state.search_term = if value.is_some() { value.unwrap().to_lowercase() } else { "".to_owned() };
-                            state.list_state.current_page = 0;
+                            state.list_state.pagination.current_page = 0;
                             state.list_state.filter_employees(&state.employees);
                         }))
                     }) value=(state.list_state.search_term) {
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use maud::{Render, Escaper};
 use std::fmt::Write;
 use std::slice::Iter;
+use paro_rs::{CanvasFrame, CommandPalette};
 use crate::router::Page;
 use crate::pages::*;
 use crate::data_generation::generate_employees;
@@ -19,6 +20,10 @@ pub struct ApplicationState {
     pub page: Page,
     pub employees: Vec<Arc<Employee>>,
     pub employee_of_the_month: Option<String>,
+    // lines drawn on the home page
+    pub sketch: Vec<Vec<(f64, f64)>>,
+    // ctrl+k
+    pub palette: CommandPalette<Jump>,
 
     pub add_state: AddState,
     pub edit_state: EditState,
@@ -32,6 +37,8 @@ impl ApplicationState {
         let result = ApplicationState {
             page: Page::Home,
             employee_of_the_month: None,
+            sketch: vec![],
+            palette: CommandPalette::new("jump").placeholder("Jump to a page or employee"),
             list_state: ListState::default(&employees),
             add_state: AddState::default(),
             edit_state: EditState::default(),
@@ -40,17 +47,106 @@ impl ApplicationState {
         return result;
     }
 
+    /**
+     * Adds the employees of a csv with the columns first name, last name, login
+     * and optionally department. Returns how many employees were imported.
+     */
+    pub fn import_csv(&mut self, csv: &str) -> usize {
+        let mut imported = 0;
+        for line in csv.lines() {
+            let columns: Vec<&str> = line.split(',').map(|column| column.trim()).collect();
+            if columns.len() < 3 || columns[0].is_empty() || columns[0].eq_ignore_ascii_case("first name") {
+                continue;
+            }
+            let department = columns.get(3)
+                .and_then(|name| Department::into_iter()
+                    .find(|department| format!("{:?}", department).eq_ignore_ascii_case(name)))
+                .copied()
+                .unwrap_or(Department::Production);
+            self.employees.push(Arc::new(Employee {
+                first_name: columns[0].to_owned(),
+                last_name: columns[1].to_owned(),
+                login: columns[2].to_owned(),
+                department,
+                ..Employee::default()
+            }));
+            imported += 1;
+        }
+        self.list_state.filter_employees(&self.employees);
+        imported
+    }
+
+    /**
+     * All employees as csv, in the format import_csv reads
+     */
+    pub fn export_csv(&self) -> String {
+        let mut csv = "first name,last name,login,department\n".to_owned();
+        for employee in &self.employees {
+            csv.push_str(&format!("{},{},{},{:?}\n", employee.first_name, employee.last_name, employee.login, employee.department));
+        }
+        csv
+    }
+
+    /**
+     * Moves the employee to the department with the given name
+     */
+    pub fn move_to_department(&mut self, employee_id: &str, department: &str) {
+        let department = match Department::into_iter().find(|known| format!("{:?}", known) == department) {
+            Some(department) => *department,
+            None => return,
+        };
+        if let Some(employee) = self.employees.iter_mut().find(|employee| employee.id == employee_id) {
+            *employee = Arc::new(Employee { department, ..(**employee).clone() });
+        }
+        self.list_state.filter_employees(&self.employees);
+    }
+
+    /**
+     * Bar chart of the employees per department as svg
+     */
+    pub fn department_chart(&self) -> String {
+        let mut svg = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"400\" height=\"130\" font-family=\"sans-serif\" font-size=\"12\">".to_owned();
+        let counts: Vec<(Department, usize)> = Department::into_iter()
+            .map(|department| (*department, self.employees.iter().filter(|employee| employee.department == *department).count()))
+            .collect();
+        let max = counts.iter().map(|(_, count)| *count).max().unwrap_or(0).max(1);
+        for (index, (department, count)) in counts.iter().enumerate() {
+            let y = index * 30 + 5;
+            svg.push_str(&format!(
+                "<text x=\"0\" y=\"{}\">{:?}</text><rect x=\"100\" y=\"{}\" width=\"{}\" height=\"20\" fill=\"#0d6efd\"/><text x=\"{}\" y=\"{}\">{}</text>",
+                y + 15, department, y, count * 250 / max, count * 250 / max + 105, y + 15, count
+            ));
+        }
+        svg.push_str("</svg>");
+        svg
+    }
 
+    pub fn draw_sketch(&self, frame: &mut CanvasFrame) {
+        frame.stroke_style("#0d6efd").line_width(3.0);
+        for line in &self.sketch {
+            frame.begin_path();
+            for (index, (x, y)) in line.iter().enumerate() {
+                if index == 0 {
+                    frame.move_to(*x, *y);
+                } else {
+                    frame.line_to(*x, *y);
+                }
+            }
+            frame.stroke();
+        }
+    }
 }
 
 // you could use &str and proper lifetimes here, I just want to keep it simple.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Hash)]
 pub struct Employee {
     pub id: String,
     pub first_name: String,
     pub last_name: String,
     pub login: String,
     pub department: Department,
+    // id of the manager
+    pub manager: Option<String>,
 }
 
 impl Default for Employee {
@@ -61,6 +157,7 @@ impl Default for Employee {
             last_name: "".to_owned(),
             login: "".to_owned(),
             department: Department::Production,
+            manager: None,
         }
     }
 }
@@ -130,7 +227,7 @@ pub enum Direction {
     Dsc,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Hash)]
 pub enum Department {
     Sales,
     Production,
@@ -15,7 +15,7 @@ use tungstenite::{Result, Message};
 
 use maud::{html};
 
-use paro_rs::{ParoApp, event};
+use paro_rs::{ParoApp, ParoError, event};
 
 /**
  * State of your pâro application.
@@ -38,9 +38,15 @@ async fn start_server() {
     let try_socket = TcpListener::bind(&addr).await;
     let listener = try_socket.expect("Failed to bind");
 
-    let paro_app = Arc::new(RwLock::new(ParoApp::<ApplicationState>::new(ApplicationState {
+    let mut paro_app = ParoApp::<ApplicationState>::new(ApplicationState {
         current_count: 0,
-    })));
+    });
+    // called instead of a callback if the value send by the client could not be parsed
+    paro_app.set_error_handler(|state: &mut ApplicationState, error: &ParoError| {
+        println!("{}", error);
+        state.current_count = 0;
+    });
+    let paro_app = Arc::new(RwLock::new(paro_app));
 
     while let Ok((stream, _)) = listener.accept().await {
         let peer = stream.peer_addr().expect("connected streams should have a peer address");
@@ -125,17 +131,9 @@ fn render_with_format(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) ->
     }));
 
     // for the input field
-    let set_counter = event!(paro_app, (move |state: &mut ApplicationState, value: Option<String>| {
-        match value {
-            None => state.current_count = 0,
-            Some(val) => {
-                match val.parse::<u64>() {
-                    Err(_) => state.current_count = 0,
-                    Ok(number) => state.current_count = number,
-                }
-            }
-        }
-        state.current_count += 1;
+    // pâro parses the value for us. Invalid input goes to the error handler set in start_server
+    let set_counter = event!(paro_app, (move |state: &mut ApplicationState, value: u64| {
+        state.current_count = value + 1;
         println!("state.current_count updated to: {}", state.current_count);
     }));
     let html = format!(
@@ -165,17 +163,9 @@ fn render_with_maud(paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>) -> St
     }));
 
     // for the input field
-    let set_counter = event!(paro_app, (move |state: &mut ApplicationState, value: Option<String>| {
-        match value {
-            None => state.current_count = 0,
-            Some(val) => {
-                match val.parse::<u64>() {
-                    Err(_) => state.current_count = 0,
-                    Ok(number) => state.current_count = number,
-                }
-            }
-        }
-        state.current_count += 1;
+    // pâro parses the value for us. Invalid input goes to the error handler set in start_server
+    let set_counter = event!(paro_app, (move |state: &mut ApplicationState, value: u64| {
+        state.current_count = value + 1;
         println!("state.current_count updated to: {}", state.current_count);
     }));
     let maud_template = html! {
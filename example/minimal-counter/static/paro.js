@@ -15,8 +15,14 @@
  * </head>
  * <body id="paro-application">
  * </body>
+ *
+ * If your app uses a Content-Security-Policy without 'unsafe-inline', configure
+ * pâro on the script tag instead and use `EventMode::Delegated` on the server:
+ *     <script src="paro.js" data-paro-websocket-url="ws://127.0.0.1:1234" data-paro-initialize></script>
  */
 (function() {
+    var paroScript = document.currentScript;
+
     PARO = {
         websocketUrl: "ws://127.0.0.1:8080",
         initialize: null,
@@ -25,9 +31,23 @@
         onCloseHandler: undefined, // (event) => {}
         onErrorHandler: undefined, // (event) => {}
         onEmitEventHandler: undefined, // (event_id) => {}
-        emitEvent: undefined, // (event_id, event) => {}
+        emitEvent: undefined, // (event_id, event, element, modifiers) => {}
         baseElementId: "paro-application",
+        // events paro.js listens for on the paro element to emit them for elements with
+        // a data-paro-<event> attribute, for example data-paro-click. See EventMode::Delegated
+        delegatedEvents: ["click", "dblclick", "contextmenu", "input", "change", "submit", "reset",
+            "keydown", "keyup", "focus", "blur", "mouseenter", "mouseleave",
+            "pointerdown", "pointermove", "pointerup", "pointercancel", "wheel"],
+        // client side effects executed after rendering, by name. See ParoCommand
+        commands: {},
+        // answers to questions of the server, by name. See ParoQuery
+        queries: {},
+        // only storage keys with this prefix are visible to ParoStorage
+        storagePrefix: "paro:",
         pingInterval: 60000,
+        toastDuration: 4000, // ms a toast is shown if it does not say otherwise, see Toast
+        uploadChunkSize: 64 * 1024, // bytes per message when uploading files, see ParoUpload
+        hotkeyHelpKey: "?", // shows the active hotkeys, null to disable. See hotkey
         logging: true,
         logger: {
             info: console.info.bind(console),
@@ -37,6 +57,43 @@
     };
     window.__PARO__ = PARO;
 
+    // messages that are not plain html start with this prefix, followed by json
+    const MESSAGE_PREFIX = "__PARO__";
+
+    var delegatedListenersInstalled = false;
+    // ids of events with the once modifier that were already emitted
+    var emittedOnce = {};
+    // elements marked as pending until the server answers, see markPending
+    var pendingElements = [];
+    // the elements files are uploaded from by upload id, pending until the server received the file
+    var unfinishedUploads = {};
+    // commands queued by paro.js itself, executed after the next render before those of the server
+    var clientCommands = [];
+    var nextUploadId = 0;
+    // object urls of the images send by ParoImages, by handle
+    var images = {};
+    // the last frame of each ParoCanvas, drawn again when the canvas was rendered again
+    var canvasFrames = {};
+    var canvasImages = {};
+    // the element with data-paro-drag that is being dragged, see on_drop
+    var draggedElement = null;
+    var dropHighlight = null;
+    var dragAndDropInstalled = false;
+    var fileDropInstalled = false;
+    var hotkeysInstalled = false;
+    var listboxesInstalled = false;
+    var dialogsInstalled = false;
+    // timers of the toasts that are shown, by id. See Toast
+    var toastTimers = {};
+    // ids of the toasts paro.js removed before the server knew
+    var dismissedToasts = {};
+    // css path of the element that was focused before a dialog opened, by dialog id. See ParoDialog
+    var dialogOpeners = {};
+    // where the last mousedown was, so selecting text in a dialog does not close it
+    var pressedElement = null;
+    // some browsers blur the focused element when rendering removes it, the user did not leave it
+    var rendering = false;
+
     
     function getCssPath(el) {
         if (!(el instanceof Element)) 
@@ -67,16 +124,952 @@
     }
 
 
+    function renderPage(html) {
+        var paroElement = document.getElementById(PARO.baseElementId);
+        if (paroElement) {
+            rendering = true;
+            paroElement.innerHTML = html;
+            rendering = false;
+            installDelegatedListeners(paroElement);
+        } else if (PARO.logging)
+            PARO.logger.error("[paro websocket message] could not find paro element '#" + PARO.baseElementId +
+            "'. Html will not be rendered!", html);
+    }
+
+
+    // listens for events on the paro element and emits them for every element with a
+    // matching data-paro-<event> attribute from the target up to the paro element,
+    // just like inline event handlers would be called. In the bubble phase, so listeners
+    // below the paro element run first and the stop modifier keeps the event from
+    // document listeners, like it does inline. Events that do not bubble are only
+    // seen while capturing.
+    function installDelegatedListeners(paroElement) {
+        if (delegatedListenersInstalled)
+            return;
+        delegatedListenersInstalled = true;
+        var notBubbling = ["focus", "blur", "mouseenter", "mouseleave"];
+        PARO.delegatedEvents.forEach((type) => {
+            var attribute = "data-paro-" + type;
+            paroElement.addEventListener(type, (event) => {
+                var element = event.target;
+                while (element && element.nodeType === Node.ELEMENT_NODE) {
+                    if (element.hasAttribute(attribute)) {
+                        // <event id> or <event id>:<modifiers>, see event!
+                        var [event_id, modifiers] = element.getAttribute(attribute).split(":");
+                        PARO.emitEvent(event_id, event, element, modifiers);
+                    }
+                    // events like focus do not bubble and the stop modifier stops bubbling
+                    if (!event.bubbles || event.cancelBubble || element === paroElement)
+                        break;
+                    element = element.parentNode;
+                }
+            }, notBubbling.includes(type));
+        });
+    }
+
+
+    // shows that an event is in flight by adding the paro-pending class and aria-busy to the
+    // element and the paro element. Elements with data-paro-disable-while-pending are disabled.
+    function markPending(element) {
+        var paroElement = document.getElementById(PARO.baseElementId);
+        [element, paroElement].forEach((pendingElement) => {
+            if (!pendingElement || !(pendingElement instanceof Element) || pendingElements.includes(pendingElement))
+                return;
+            var disable = pendingElement.hasAttribute("data-paro-disable-while-pending") && !pendingElement.disabled;
+            pendingElement.classList.add("paro-pending");
+            pendingElement.setAttribute("aria-busy", "true");
+            if (disable)
+                pendingElement.disabled = true;
+            pendingElement.paroDisabledWhilePending = disable;
+            pendingElements.push(pendingElement);
+        });
+    }
+
+
+    // called once the server answered or the connection is gone. Elements replaced by a
+    // render are cleared as well, in case they are still referenced somewhere. Elements
+    // files are uploaded from stay pending, along with the paro element, until the server
+    // received the files
+    function clearPending() {
+        var uploading = Object.values(unfinishedUploads);
+        if (uploading.length > 0)
+            uploading.push(document.getElementById(PARO.baseElementId));
+        pendingElements = pendingElements.filter((pendingElement) => {
+            if (uploading.includes(pendingElement))
+                return true;
+            pendingElement.classList.remove("paro-pending");
+            pendingElement.removeAttribute("aria-busy");
+            if (pendingElement.paroDisabledWhilePending)
+                pendingElement.disabled = false;
+            pendingElement.paroDisabledWhilePending = false;
+            return false;
+        });
+    }
+
+
+    // swaps the content of all elements with a matching data-paro-region attribute
+    function renderRegions(regions) {
+        for (var name in regions) {
+            var regionElements = document.querySelectorAll('[data-paro-region="' + CSS.escape(name) + '"]');
+            if (regionElements.length == 0 && PARO.logging)
+                PARO.logger.error("[paro websocket message] could not find region '" + name +
+                "'. Html will not be rendered!", regions[name]);
+            rendering = true;
+            regionElements.forEach((regionElement) => regionElement.innerHTML = regions[name]);
+            rendering = false;
+        }
+    }
+
+
+    // the overlay of the toasts, outside of the paro element, so rendering does not remove it.
+    // Style it via the paro-toasts, paro-toast and paro-toast-<level> classes
+    function toastContainer() {
+        var toasts = document.querySelector(".paro-toasts");
+        if (!toasts) {
+            toasts = document.createElement("div");
+            toasts.className = "paro-toasts";
+            toasts.setAttribute("aria-live", "polite");
+            document.body.appendChild(toasts);
+        }
+        return toasts;
+    }
+
+    // shows the toasts rendered by the server. Toasts that are shown already stay as they are,
+    // so their time keeps running and screen readers do not read them again
+    function renderToasts(html) {
+        var toasts = toastContainer();
+        var template = document.createElement("template");
+        template.innerHTML = html;
+        var rendered = Array.from(template.content.children);
+        var ids = rendered.map((toast) => toast.getAttribute("data-paro-toast"));
+        // the server did not know yet that we removed them
+        Object.keys(dismissedToasts).forEach((id) => {
+            if (!ids.includes(id))
+                delete dismissedToasts[id];
+        });
+        Array.from(toasts.children).forEach((toast) => {
+            var id = toast.getAttribute("data-paro-toast");
+            if (ids.includes(id))
+                return;
+            clearTimeout(toastTimers[id]);
+            delete toastTimers[id];
+            toast.remove();
+        });
+        rendered.forEach((toast) => {
+            var id = toast.getAttribute("data-paro-toast");
+            if (dismissedToasts[id] || toasts.querySelector('[data-paro-toast="' + CSS.escape(id) + '"]'))
+                return;
+            toasts.appendChild(toast);
+            var close = toast.querySelector("[data-paro-toast-close]");
+            if (close)
+                close.addEventListener("click", () => dismissToast(toast, "closed"));
+            if (!toast.hasAttribute("data-paro-toast-sticky")) {
+                var duration = Number(toast.getAttribute("data-paro-toast-duration") || PARO.toastDuration);
+                toastTimers[id] = setTimeout(() => dismissToast(toast, "timeout"), duration);
+            }
+        });
+    }
+
+    // removes the toast right away and tells the server, which calls back whoever showed it
+    function dismissToast(toast, reason) {
+        var id = toast.getAttribute("data-paro-toast");
+        clearTimeout(toastTimers[id]);
+        delete toastTimers[id];
+        dismissedToasts[id] = true;
+        toast.remove();
+        if (PARO.websocket)
+            sendMessage({ dismissToast: { toast: id, reason: reason } });
+    }
+
+
+    function querySelectorOrLog(selector, command) {
+        var element = document.querySelector(selector);
+        if (!element && PARO.logging)
+            PARO.logger.error("[paro command] could not find element '" + selector + "' for command " + command.command, command);
+        return element;
+    }
+
+    PARO.commands.focus = (command) => {
+        var element = querySelectorOrLog(command.selector, command);
+        if (element)
+            element.focus();
+    };
+
+    PARO.commands.scrollIntoView = (command) => {
+        var element = querySelectorOrLog(command.selector, command);
+        if (element)
+            element.scrollIntoView({ behavior: "smooth", block: "nearest" });
+    };
+
+    PARO.commands.setTitle = (command) => {
+        document.title = command.title;
+    };
+
+    PARO.commands.copyToClipboard = (command) => {
+        navigator.clipboard.writeText(command.text).catch((error) => {
+            if (PARO.logging)
+                PARO.logger.error("[paro command] could not copy to clipboard", error);
+        });
+    };
+
+    PARO.commands.animate = (command) => {
+        var element = querySelectorOrLog(command.selector, command);
+        if (!element)
+            return;
+        element.classList.remove(command.class);
+        // forces a reflow, so the animation starts again if the class was already set
+        void element.offsetWidth;
+        element.classList.add(command.class);
+        element.addEventListener("animationend", () => element.classList.remove(command.class), { once: true });
+    };
+
+    function base64ToBlob(data, mime) {
+        var binary = atob(data);
+        var bytes = new Uint8Array(binary.length);
+        for (var i = 0; i < binary.length; i++)
+            bytes[i] = binary.charCodeAt(i);
+        return new Blob([bytes], { type: mime });
+    }
+
+    PARO.commands.download = (command) => {
+        var url = URL.createObjectURL(base64ToBlob(command.data, command.mime));
+        var link = document.createElement("a");
+        link.href = url;
+        link.download = command.name;
+        link.style.display = "none";
+        document.body.appendChild(link);
+        link.click();
+        link.remove();
+        // the download may not have started yet
+        setTimeout(() => URL.revokeObjectURL(url), 60000);
+    };
+
+    // object urls shown via showBlob by selector, so they can be released when replaced
+    var shownObjectUrls = {};
+
+    PARO.commands.showBlob = (command) => {
+        var element = querySelectorOrLog(command.selector, command);
+        if (!element)
+            return;
+        if (shownObjectUrls[command.selector])
+            URL.revokeObjectURL(shownObjectUrls[command.selector]);
+        shownObjectUrls[command.selector] = URL.createObjectURL(base64ToBlob(command.data, command.mime));
+        element.src = shownObjectUrls[command.selector];
+    };
+
+    // queued by emitEvent, so the element keeps focus and caret position when the page is rendered again
+    PARO.commands.restoreFocus = (command) => {
+        var element = document.querySelector(command.selector);
+        if (element) {
+            element.focus();
+            if (command.selectionStart != null) {
+                element.selectionStart = command.selectionStart;
+                element.selectionEnd = command.selectionEnd;
+            }
+        }
+    };
+
+    function executeCommands(commands) {
+        commands.forEach((command) => {
+            var execute = PARO.commands[command.command];
+            if (execute)
+                execute(command);
+            else if (PARO.logging)
+                PARO.logger.error("[paro command] unknown command " + command.command, command);
+        });
+    }
+
+
+    PARO.queries.elementSize = (query) => {
+        var element = document.querySelector(query.selector);
+        if (!element)
+            return null;
+        var rect = element.getBoundingClientRect();
+        return { x: rect.x, y: rect.y, width: rect.width, height: rect.height };
+    };
+
+    PARO.queries.textSelection = (query) => {
+        var element = document.activeElement;
+        if (element && typeof element.selectionStart === "number" && element.value !== undefined)
+            return element.value.substring(element.selectionStart, element.selectionEnd);
+        return window.getSelection().toString();
+    };
+
+    PARO.queries.scrollOffset = (query) => {
+        var element = query.selector ? document.querySelector(query.selector) : null;
+        if (query.selector && !element)
+            throw "could not find element '" + query.selector + "'";
+        return element ? { x: element.scrollLeft, y: element.scrollTop } : { x: window.scrollX, y: window.scrollY };
+    };
+
+    PARO.queries.localStorage = (query) => localStorage.getItem(query.key);
+
+    // sends a message that is not an event
+    function sendMessage(message) {
+        PARO.websocket.send(MESSAGE_PREFIX + JSON.stringify(message));
+    }
+
+    function sendReply(reply) {
+        sendMessage({ reply: reply });
+    }
+
+    // answers a question of the server. Answers may be promises
+    function answerQuery(query) {
+        var answer = PARO.queries[query.query];
+        if (!answer) {
+            sendReply({ id: query.id, error: "unknown query " + query.query });
+            return;
+        }
+        try {
+            Promise.resolve(answer(query)).then(
+                (value) => sendReply({ id: query.id, value: value === undefined ? null : value }),
+                (error) => sendReply({ id: query.id, error: String(error) })
+            );
+        } catch (error) {
+            sendReply({ id: query.id, error: String(error) });
+        }
+    }
+
+
+    function storageArea(area) {
+        try {
+            return window[area];
+        } catch (error) {
+            // storage can be disabled
+            if (PARO.logging)
+                PARO.logger.error("[paro storage] " + area + " is not available", error);
+            return null;
+        }
+    }
+
+    // the values of ParoStorage, send when connecting
+    function readStorage(area) {
+        var storage = storageArea(area);
+        var values = {};
+        if (!storage)
+            return values;
+        for (var i = 0; i < storage.length; i++) {
+            var key = storage.key(i);
+            if (key.startsWith(PARO.storagePrefix))
+                values[key.substring(PARO.storagePrefix.length)] = storage.getItem(key);
+        }
+        return values;
+    }
+
+    function writeStorage(write) {
+        var storage = storageArea(write.area == "session" ? "sessionStorage" : "localStorage");
+        if (!storage)
+            return;
+        if (write.value === null)
+            storage.removeItem(PARO.storagePrefix + write.key);
+        else
+            storage.setItem(PARO.storagePrefix + write.key, write.value);
+    }
+
+
+    var IMAGE_PREFIX = "paro-image://";
+
+    // binary messages are images: a line of json describing the image, followed by its bytes
+    function receiveImage(buffer) {
+        var bytes = new Uint8Array(buffer);
+        var newline = bytes.indexOf(10);
+        var header = JSON.parse(new TextDecoder().decode(bytes.subarray(0, newline)));
+        if (images[header.image])
+            URL.revokeObjectURL(images[header.image]);
+        images[header.image] = URL.createObjectURL(new Blob([bytes.subarray(newline + 1)], { type: header.mime }));
+        resolveImages();
+    }
+
+    function releaseImage(handle) {
+        if (images[handle])
+            URL.revokeObjectURL(images[handle]);
+        delete images[handle];
+    }
+
+    // points src="paro-image://<handle>" and data-paro-src="<handle>" to the cached images.
+    // Images which did not arrive yet are resolved once they do
+    function resolveImages() {
+        document.querySelectorAll('[src^="' + IMAGE_PREFIX + '"], [data-paro-src], [data-paro-image]').forEach((element) => {
+            var handle = element.getAttribute("data-paro-src") || element.getAttribute("src");
+            if (!handle.startsWith(IMAGE_PREFIX) && element.hasAttribute("data-paro-image"))
+                handle = element.getAttribute("data-paro-image");
+            if (handle.startsWith(IMAGE_PREFIX))
+                handle = handle.substring(IMAGE_PREFIX.length);
+            // remembered, so replacing a named image updates the element as well
+            element.setAttribute("data-paro-image", handle);
+            if (images[handle] && element.getAttribute("src") != images[handle])
+                element.setAttribute("src", images[handle]);
+        });
+    }
+
+
+    function drawCanvasFrame(frame) {
+        canvasFrames[frame.canvas] = frame.ops;
+        document.querySelectorAll('canvas[data-paro-canvas="' + CSS.escape(frame.canvas) + '"]')
+            .forEach((canvas) => drawOps(canvas, frame.ops));
+    }
+
+    function redrawCanvases() {
+        document.querySelectorAll("canvas[data-paro-canvas]").forEach((canvas) => {
+            var ops = canvasFrames[canvas.getAttribute("data-paro-canvas")];
+            if (ops)
+                drawOps(canvas, ops);
+        });
+    }
+
+    // ops are [name, arguments..] of CanvasRenderingContext2D, either a method or a property
+    function drawOps(canvas, ops) {
+        var context = canvas.getContext("2d");
+        context.setTransform(1, 0, 0, 1, 0, 0);
+        context.clearRect(0, 0, canvas.width, canvas.height);
+        context.save();
+        ops.forEach(([name, ...args]) => {
+            if (name == "image") {
+                var image = canvasImage(args[0]);
+                if (image)
+                    context.drawImage(image, ...args.slice(1));
+            } else if (typeof context[name] == "function") {
+                context[name](...args);
+            } else {
+                context[name] = args[0];
+            }
+        });
+        context.restore();
+    }
+
+    // images of ParoImages that can be drawn, null until they are loaded
+    function canvasImage(handle) {
+        if (!images[handle])
+            return null;
+        var image = canvasImages[handle];
+        if (!image || image.src != images[handle]) {
+            image = new Image();
+            image.onload = redrawCanvases;
+            image.src = images[handle];
+            canvasImages[handle] = image;
+        }
+        return image.complete ? image : null;
+    }
+
+    // the position of a pointer event in canvas pixels, see CanvasPointer
+    function canvasPointer(canvas, event) {
+        var rect = canvas.getBoundingClientRect();
+        return JSON.stringify({
+            event: event.type,
+            x: (event.clientX - rect.left) * (canvas.width || rect.width) / (rect.width || 1),
+            y: (event.clientY - rect.top) * (canvas.height || rect.height) / (rect.height || 1),
+            button: event.button || 0,
+            buttons: event.buttons || 0
+        });
+    }
+
+
+    function dropZone(event) {
+        var zone = event.target instanceof Element ? event.target.closest("[data-paro-drop]") : null;
+        return draggedElement && zone && zone !== draggedElement ? zone : null;
+    }
+
+    // the accepted position closest to where the pointer is, see DropPosition
+    function dropPosition(zone, event) {
+        var positions = (zone.getAttribute("data-paro-drop").split(":")[1] || "inside").split(",");
+        var rect = zone.getBoundingClientRect();
+        var offset = (event.clientY - rect.top) / (rect.height || 1);
+        var edge = positions.includes("inside") ? 0.25 : 0.5;
+        if (positions.includes("before") && offset < edge)
+            return "before";
+        if (positions.includes("after") && offset >= 1 - edge)
+            return "after";
+        if (positions.includes("inside"))
+            return "inside";
+        return positions.includes("before") && offset < 0.5 ? "before" : positions[positions.length - 1];
+    }
+
+    function highlightDrop(zone, position) {
+        if (dropHighlight)
+            dropHighlight.classList.remove("paro-drop-before", "paro-drop-after", "paro-drop-inside");
+        dropHighlight = zone;
+        if (zone)
+            zone.classList.add("paro-drop-" + position);
+    }
+
+    function endDrag() {
+        highlightDrop(null);
+        if (draggedElement)
+            draggedElement.classList.remove("paro-dragging");
+        draggedElement = null;
+    }
+
+    function installDragAndDrop() {
+        if (dragAndDropInstalled)
+            return;
+        dragAndDropInstalled = true;
+        document.addEventListener("dragstart", (event) => {
+            var element = event.target instanceof Element ? event.target.closest("[data-paro-drag]") : null;
+            if (!element)
+                return;
+            draggedElement = element;
+            element.classList.add("paro-dragging");
+            event.dataTransfer.effectAllowed = "move";
+            event.dataTransfer.setData("text/plain", element.getAttribute("data-paro-drag"));
+        });
+        document.addEventListener("dragover", (event) => {
+            var zone = dropZone(event);
+            if (!zone)
+                return;
+            // allows dropping
+            event.preventDefault();
+            event.dataTransfer.dropEffect = "move";
+            highlightDrop(zone, dropPosition(zone, event));
+        });
+        document.addEventListener("dragleave", (event) => {
+            if (dropHighlight && !dropHighlight.contains(event.relatedTarget))
+                highlightDrop(null);
+        });
+        document.addEventListener("drop", (event) => {
+            var zone = dropZone(event);
+            if (!zone)
+                return;
+            event.preventDefault();
+            var drop = {
+                payload: draggedElement.getAttribute("data-paro-drag"),
+                target: zone.getAttribute("data-paro-drop-target"),
+                position: dropPosition(zone, event)
+            };
+            endDrag();
+            if (!PARO.websocket)
+                return;
+            PARO.websocket.send(zone.getAttribute("data-paro-drop").split(":")[0] + "__PARO__" + JSON.stringify(drop));
+            markPending(zone);
+        });
+        document.addEventListener("dragend", endDrag);
+    }
+
+
+    // uploads files dropped or pasted on an element with data-paro-file-drop, see file_drop
+    function uploadTo(zone, files) {
+        var [event_id, modifiers] = zone.getAttribute("data-paro-file-drop").split(":");
+        modifiers = parseModifiers(modifiers);
+        if (!PARO.websocket || files.length == 0)
+            return;
+        uploadFiles(zone, event_id, files, modifiers.maxSize, modifiers.accept);
+    }
+
+    function fileDropZone(event) {
+        var zone = event.target instanceof Element ? event.target.closest("[data-paro-file-drop]") : null;
+        var carriesFiles = event.dataTransfer && Array.from(event.dataTransfer.types).includes("Files");
+        return !draggedElement && carriesFiles ? zone : null;
+    }
+
+    function installFileDrop() {
+        if (fileDropInstalled)
+            return;
+        fileDropInstalled = true;
+        document.addEventListener("dragover", (event) => {
+            var zone = fileDropZone(event);
+            document.querySelectorAll(".paro-file-over").forEach((element) => {
+                if (element !== zone)
+                    element.classList.remove("paro-file-over");
+            });
+            if (!zone)
+                return;
+            event.preventDefault();
+            event.dataTransfer.dropEffect = "copy";
+            zone.classList.add("paro-file-over");
+        });
+        document.addEventListener("dragleave", (event) => {
+            var zone = fileDropZone(event);
+            if (zone && !zone.contains(event.relatedTarget))
+                zone.classList.remove("paro-file-over");
+        });
+        document.addEventListener("drop", (event) => {
+            var zone = fileDropZone(event);
+            if (!zone)
+                return;
+            event.preventDefault();
+            zone.classList.remove("paro-file-over");
+            uploadTo(zone, Array.from(event.dataTransfer.files));
+        });
+        document.addEventListener("paste", (event) => {
+            var files = Array.from((event.clipboardData && event.clipboardData.files) || []);
+            if (files.length == 0)
+                return;
+            var focused = document.activeElement;
+            var zone = (focused && focused.closest("[data-paro-file-drop]")) || document.querySelector("[data-paro-file-drop]");
+            if (!zone)
+                return;
+            event.preventDefault();
+            uploadTo(zone, files);
+        });
+    }
+
+
+    // the keys of the event like hotkey declares them, once with shift and once without, as
+    // shift is part of keys like "?"
+    function eventKeys(event) {
+        var key = event.key.toLowerCase();
+        var modifiers = [];
+        if (event.ctrlKey) modifiers.push("ctrl");
+        if (event.shiftKey) modifiers.push("shift");
+        if (event.altKey) modifiers.push("alt");
+        if (event.metaKey) modifiers.push("meta");
+        var withoutShift = modifiers.filter((modifier) => modifier != "shift");
+        return [modifiers.concat([key]).join("+"), key.length == 1 ? withoutShift.concat([key]).join("+") : null];
+    }
+
+    function isTyping(element) {
+        return element instanceof Element &&
+            (element.matches("input, textarea, select") || element.isContentEditable);
+    }
+
+    // plain keys would get in the way of typing
+    function allowedWhileTyping(keys) {
+        var parts = keys.split("+");
+        var key = parts.pop();
+        return key == "escape" || parts.some((modifier) => modifier != "shift");
+    }
+
+    // the hotkeys declared on the page whose scope contains the focus, best first
+    function activeHotkeys() {
+        var focused = document.activeElement;
+        var dialog = topDialog();
+        return Array.from(document.querySelectorAll("template[data-paro-hotkey]"))
+            .filter((hotkey) => {
+                // the page behind a dialog is out of reach
+                if (dialog && !dialog.contains(hotkey))
+                    return false;
+                var scope = hotkey.getAttribute("data-paro-hotkey-scope");
+                try {
+                    return !scope || (focused && focused.closest(scope) !== null);
+                } catch (error) {
+                    if (PARO.logging)
+                        PARO.logger.error("[paro hotkey] invalid scope '" + scope + "'", error);
+                    return false;
+                }
+            })
+            .sort((a, b) =>
+                Number(b.getAttribute("data-paro-hotkey-priority")) - Number(a.getAttribute("data-paro-hotkey-priority")) ||
+                Number(b.hasAttribute("data-paro-hotkey-scope")) - Number(a.hasAttribute("data-paro-hotkey-scope")));
+    }
+
+    // lists the active hotkeys. Style it via the paro-hotkeys class
+    function toggleHotkeyHelp() {
+        var help = document.querySelector(".paro-hotkeys");
+        if (help) {
+            help.remove();
+            return;
+        }
+        help = document.createElement("div");
+        help.className = "paro-hotkeys";
+        help.setAttribute("role", "dialog");
+        help.setAttribute("aria-label", "Keyboard shortcuts");
+        var list = document.createElement("dl");
+        var listed = [];
+        activeHotkeys().forEach((hotkey) => {
+            var keys = hotkey.getAttribute("data-paro-hotkey");
+            // only the one that would be called
+            if (listed.includes(keys))
+                return;
+            listed.push(keys);
+            var term = document.createElement("dt");
+            var kbd = document.createElement("kbd");
+            kbd.textContent = keys.replace(/\+ $/, "+space").replace(/^ $/, "space");
+            term.appendChild(kbd);
+            var description = document.createElement("dd");
+            description.textContent = hotkey.getAttribute("data-paro-hotkey-description");
+            list.appendChild(term);
+            list.appendChild(description);
+        });
+        help.appendChild(list);
+        document.body.appendChild(help);
+    }
+
+    // inputs with data-paro-listbox="<id of a listbox>" move through its [role=option] elements
+    // with the arrow keys and choose one with Enter by clicking it, without asking the server
+    function listboxOptions(input) {
+        var listbox = document.getElementById(input.getAttribute("data-paro-listbox"));
+        return listbox ? Array.from(listbox.querySelectorAll('[role="option"]')) : [];
+    }
+
+    function activateOption(input, options, active) {
+        options.forEach((option) => {
+            option.setAttribute("aria-selected", option === active ? "true" : "false");
+            option.classList.toggle("paro-active", option === active);
+        });
+        input.setAttribute("aria-activedescendant", active.id);
+        active.scrollIntoView({ block: "nearest" });
+    }
+
+    function installListboxes() {
+        if (listboxesInstalled)
+            return;
+        listboxesInstalled = true;
+        document.addEventListener("keydown", (event) => {
+            var input = event.target;
+            if (!(input instanceof Element) || !input.hasAttribute("data-paro-listbox") || event.isComposing)
+                return;
+            var options = listboxOptions(input);
+            var active = options.findIndex((option) => option.getAttribute("aria-selected") == "true");
+            if ((event.key == "ArrowDown" || event.key == "ArrowUp") && options.length > 0) {
+                event.preventDefault();
+                var step = event.key == "ArrowDown" ? 1 : -1;
+                var next = active < 0 ? (step > 0 ? 0 : options.length - 1) : (active + step + options.length) % options.length;
+                activateOption(input, options, options[next]);
+            } else if (event.key == "Enter" && active >= 0) {
+                event.preventDefault();
+                options[active].click();
+            }
+        });
+        // clicking an option must not move the focus away from the input
+        document.addEventListener("mousedown", (event) => {
+            var option = event.target instanceof Element ? event.target.closest('[role="option"]') : null;
+            var listbox = option ? option.closest('[role="listbox"]') : null;
+            if (listbox && listbox.id && document.querySelector('[data-paro-listbox="' + CSS.escape(listbox.id) + '"]'))
+                event.preventDefault();
+        });
+    }
+
+    // the user may have typed on while the server was rendering the suggestions for what was
+    // send before. What was typed since then is kept, so the caret does not jump back
+    function typedValue() {
+        var focused = document.activeElement;
+        if (!(focused instanceof Element) || !focused.hasAttribute("data-paro-listbox") || focused.value === focused.paroSentValue)
+            return null;
+        return {
+            selector: getCssPath(focused),
+            value: focused.value,
+            selectionStart: focused.selectionStart,
+            selectionEnd: focused.selectionEnd
+        };
+    }
+
+    function restoreTypedValue(typed) {
+        if (!typed)
+            return;
+        var input = document.querySelector(typed.selector);
+        if (!input || input !== document.activeElement || !input.hasAttribute("data-paro-listbox"))
+            return;
+        input.value = typed.value;
+        input.setSelectionRange(typed.selectionStart, typed.selectionEnd);
+    }
+
+    // the backdrop of the dialog on top, only its content can be used
+    function topDialog() {
+        var backdrops = document.querySelectorAll(".paro-dialogs > [data-paro-dialog]");
+        return backdrops.length > 0 ? backdrops[backdrops.length - 1] : null;
+    }
+
+    function focusableElements(container) {
+        return Array.from(container.querySelectorAll('a[href], button, input, select, textarea, [tabindex], [contenteditable="true"]'))
+            .filter((element) => !element.disabled && element.tabIndex >= 0 && element.getClientRects().length > 0);
+    }
+
+    // focuses the element at path if it is part of the dialog, otherwise what the dialog wants focused first
+    function focusDialog(backdrop, path) {
+        var dialog = backdrop.querySelector('[role="dialog"]');
+        var element = path ? document.querySelector(path) : null;
+        if (!element || !dialog.contains(element))
+            element = dialog.querySelector("[autofocus]") || focusableElements(dialog)[0] || dialog;
+        element.focus();
+    }
+
+    // dialogs are rendered into a container at the end of the paro element, so they stay
+    // when only regions are rendered. focused is the css path of the element focused before rendering
+    function renderDialogs(html, focused) {
+        var paroElement = document.getElementById(PARO.baseElementId);
+        if (!paroElement)
+            return;
+        var container = paroElement.querySelector(":scope > .paro-dialogs");
+        if (!container) {
+            container = document.createElement("div");
+            container.className = "paro-dialogs";
+            paroElement.appendChild(container);
+        }
+        rendering = true;
+        container.innerHTML = html;
+        rendering = false;
+        var open = Array.from(container.querySelectorAll(":scope > [data-paro-dialog]"))
+            .map((backdrop) => backdrop.getAttribute("data-paro-dialog"));
+        // the focus goes back to where it was before the first of the closed dialogs opened
+        var opener = null;
+        Object.keys(dialogOpeners).forEach((id) => {
+            if (open.includes(id))
+                return;
+            if (opener === null)
+                opener = dialogOpeners[id];
+            delete dialogOpeners[id];
+        });
+        open.forEach((id) => {
+            if (!(id in dialogOpeners))
+                dialogOpeners[id] = focused;
+        });
+        var top = topDialog();
+        if (top && !top.contains(document.activeElement)) {
+            focusDialog(top, opener || focused);
+        } else if (!top && opener) {
+            var element = document.querySelector(opener);
+            if (element)
+                element.focus();
+        }
+    }
+
+    function dismissDialog(backdrop) {
+        if (!backdrop.hasAttribute("data-paro-dismissible") || !PARO.websocket)
+            return;
+        if (PARO.logging)
+            PARO.logger.info("[paro dialog] dismissing " + backdrop.getAttribute("data-paro-dialog"));
+        sendMessage({ dismissDialog: { dialog: backdrop.getAttribute("data-paro-dialog") } });
+    }
+
+    // keeps the focus inside the dialog on top and closes it via Escape or a click on its backdrop
+    function installDialogs() {
+        if (dialogsInstalled)
+            return;
+        dialogsInstalled = true;
+        document.addEventListener("keydown", (event) => {
+            var top = topDialog();
+            if (!top || event.defaultPrevented || event.isComposing || document.querySelector(".paro-hotkeys"))
+                return;
+            if (event.key == "Escape") {
+                // not meant for the page behind the dialog, even if the dialog stays open
+                event.preventDefault();
+                dismissDialog(top);
+            } else if (event.key == "Tab") {
+                var focusable = focusableElements(top);
+                var index = focusable.indexOf(document.activeElement);
+                if (focusable.length == 0) {
+                    event.preventDefault();
+                } else if (event.shiftKey && index <= 0) {
+                    event.preventDefault();
+                    focusable[focusable.length - 1].focus();
+                } else if (!event.shiftKey && (index < 0 || index == focusable.length - 1)) {
+                    event.preventDefault();
+                    focusable[0].focus();
+                }
+            }
+        });
+        document.addEventListener("focusin", (event) => {
+            var top = topDialog();
+            if (top && !rendering && !top.contains(event.target))
+                focusDialog(top, null);
+        });
+        document.addEventListener("mousedown", (event) => pressedElement = event.target);
+        document.addEventListener("click", (event) => {
+            var top = topDialog();
+            if (top && event.target === top && pressedElement === top)
+                dismissDialog(top);
+        });
+    }
+
+    // a single listener for all hotkeys, see hotkey
+    function installHotkeys() {
+        if (hotkeysInstalled)
+            return;
+        hotkeysInstalled = true;
+        document.addEventListener("keydown", (event) => {
+            if (event.defaultPrevented || event.isComposing)
+                return;
+            var [keys, keysWithoutShift] = eventKeys(event);
+            var typing = isTyping(event.target);
+            var help = document.querySelector(".paro-hotkeys");
+            if (help && keys == "escape") {
+                help.remove();
+                event.preventDefault();
+                return;
+            }
+            var hotkey = activeHotkeys().find((hotkey) => {
+                var declared = hotkey.getAttribute("data-paro-hotkey");
+                return (declared == keys || declared == keysWithoutShift) && (!typing || allowedWhileTyping(declared));
+            });
+            if (hotkey) {
+                event.preventDefault();
+                if (help)
+                    help.remove();
+                if (!PARO.websocket)
+                    return;
+                if (PARO.logging)
+                    PARO.logger.info("[paro hotkey] " + keys);
+                PARO.websocket.send(hotkey.getAttribute("data-paro-hotkey-call") + "__PARO__");
+                markPending(document.activeElement);
+            } else if (PARO.hotkeyHelpKey && !typing && (keys == PARO.hotkeyHelpKey || keysWithoutShift == PARO.hotkeyHelpKey)) {
+                event.preventDefault();
+                toggleHotkeyHelp();
+            }
+        });
+    }
+
+
+    function toBase64(buffer) {
+        var bytes = new Uint8Array(buffer);
+        var binary = "";
+        // in parts, as functions can only take so many arguments
+        for (var i = 0; i < bytes.length; i += 0x8000)
+            binary += String.fromCharCode.apply(null, bytes.subarray(i, i + 0x8000));
+        return btoa(binary);
+    }
+
+    // accept is a list like "image/*,.csv", see ParoUpload::accept
+    function acceptsFile(file, accept) {
+        if (!accept)
+            return true;
+        var mime = file.type.toLowerCase();
+        var name = file.name.toLowerCase();
+        return accept.split(",").some((accepted) => {
+            if (accepted.startsWith("."))
+                return name.endsWith(accepted);
+            if (accepted.endsWith("/*"))
+                return mime.split("/")[0] == accepted.slice(0, -2);
+            return mime == accepted;
+        });
+    }
+
+    // streams the files to the server in chunks, one after another. See ParoUpload. The
+    // element stays pending until the server received or rejected all of them
+    async function uploadFiles(element, event_id, files, maxSize, accept) {
+        if (files.length == 0)
+            return;
+        markPending(element);
+        for (var file of files) {
+            var upload = "upload-" + (++nextUploadId);
+            unfinishedUploads[upload] = element;
+            if (PARO.logging)
+                PARO.logger.info("[paro upload] uploading '" + file.name + "' (" + file.size + " bytes)");
+            sendMessage({ uploadStart: { callback: event_id, upload: upload, name: file.name, mime: file.type, size: file.size } });
+            // the server rejects files that are too large, no need to send them
+            if ((maxSize !== undefined && file.size > Number(maxSize)) || !acceptsFile(file, accept))
+                continue;
+            for (var offset = 0; offset < file.size; offset += PARO.uploadChunkSize) {
+                var chunk = await file.slice(offset, offset + PARO.uploadChunkSize).arrayBuffer();
+                // do not read the whole file into the send buffer of the websocket
+                while (PARO.websocket.bufferedAmount > 16 * PARO.uploadChunkSize)
+                    await new Promise((resolve) => setTimeout(resolve, 10));
+                sendMessage({ uploadChunk: { upload: upload, data: toBase64(chunk) } });
+            }
+            sendMessage({ uploadEnd: { upload: upload } });
+        }
+    }
+
+
     // gives you a chance to change pâro settings. You have to call initialize once you configured pâro
     PARO.initialize = () => {
         PARO = window.__PARO__;
         if (PARO.logging) PARO.logger.info("[paro init] connecting to websocket via " + PARO.websocketUrl);
         let socket = new WebSocket(PARO.websocketUrl);
+        socket.binaryType = "arraybuffer";
+        installDragAndDrop();
+        installFileDrop();
+        // before the hotkeys, arrow keys and Enter in a listbox input are not meant for them
+        installListboxes();
+        // before the hotkeys as well, Escape closes the dialog and does not reach the page
+        installDialogs();
+        installHotkeys();
 
         socket.onopen = function(event) {
             if (PARO.logging) PARO.logger.info("[paro open] Connection established", event);
             PARO.websocket = socket;
             if (PARO.onOpenHandler) PARO.onOpenHandler(event);
+            socket.send(MESSAGE_PREFIX + JSON.stringify({
+                hello: { local: readStorage("localStorage"), session: readStorage("sessionStorage") }
+            }));
             socket.send("ping");
         };
 
@@ -88,14 +1081,47 @@
                 PARO.logger.info("[paro websocket message] Data received from server:", event);
             if (PARO.onMessageHandler)
                 PARO.onMessageHandler(event);
+            if (event.data instanceof ArrayBuffer) {
+                receiveImage(event.data);
+                return;
+            }
             if (event.data == "pong")
                 return;
-            var paroElement = document.getElementById(PARO.baseElementId);
-            if (paroElement)
-                paroElement.innerHTML = event.data;
-            else if (PARO.logging)
-                PARO.logger.error("[paro websocket message] could not find paro element '#" + PARO.baseElementId +
-                "'. Html will not be rendered!", event);
+            var message = {};
+            if (event.data.startsWith(MESSAGE_PREFIX)) {
+                message = JSON.parse(event.data.substring(MESSAGE_PREFIX.length));
+                // queries and storage writes are send on their own and are no answer to an event
+                if (message.queries || message.storage || message.releasedImages || message.canvas) {
+                    (message.queries || []).forEach(answerQuery);
+                    (message.storage || []).forEach(writeStorage);
+                    (message.releasedImages || []).forEach(releaseImage);
+                    (message.canvas || []).forEach(drawCanvasFrame);
+                    return;
+                }
+            }
+            var typed = typedValue();
+            var focused = getCssPath(document.activeElement);
+            (message.finishedUploads || []).forEach((upload) => delete unfinishedUploads[upload]);
+            clearPending();
+            if (event.data.startsWith(MESSAGE_PREFIX)) {
+                if (message.html !== undefined)
+                    renderPage(message.html);
+                if (message.regions)
+                    renderRegions(message.regions);
+                if (message.dialogs !== undefined)
+                    renderDialogs(message.dialogs, focused);
+            } else {
+                renderPage(event.data);
+            }
+            resolveImages();
+            redrawCanvases();
+            // our own commands first, so the server has the last word on focus
+            var commands = clientCommands;
+            clientCommands = [];
+            executeCommands(commands.concat(message.commands || []));
+            restoreTypedValue(typed);
+            if (message.toasts !== undefined)
+                renderToasts(message.toasts);
         };
         
 
@@ -110,6 +1136,8 @@
             if (PARO.logging)
             PARO.logger.info("[paro websocket close] Connection died", event);
         }
+        unfinishedUploads = {};
+        clearPending();
         if (PARO.onCloseHandler)
             PARO.onCloseHandler(event);
         };
@@ -123,47 +1151,119 @@
     }
 
 
+    // modifiers look like "prevent,stop,key=Enter" with percent encoded values, see event!
+    function parseModifiers(modifiers) {
+        var parsed = {};
+        if (modifiers)
+            modifiers.split(",").forEach((modifier) => {
+                var [name, value] = modifier.split("=");
+                parsed[name] = value === undefined ? true : decodeURIComponent(value);
+            });
+        return parsed;
+    }
+
+
+    // applies the modifiers to the event and returns false if the event should not be emitted
+    function applyModifiers(event_id, event, element, modifiers) {
+        if (modifiers.self && event.target !== element)
+            return false;
+        if (modifiers.key !== undefined && event.key !== modifiers.key)
+            return false;
+        if ((modifiers.ctrl && !event.ctrlKey) || (modifiers.shift && !event.shiftKey) ||
+            (modifiers.alt && !event.altKey) || (modifiers.meta && !event.metaKey))
+            return false;
+        if (modifiers.pressed && !event.buttons)
+            return false;
+        // still prevented after the first time, so a form does not reload the page on the second submit
+        if (modifiers.prevent)
+            event.preventDefault();
+        if (modifiers.stop)
+            event.stopPropagation();
+        if (modifiers.once) {
+            if (emittedOnce[event_id])
+                return false;
+            emittedOnce[event_id] = true;
+        }
+        return true;
+    }
+
+
     /**
-     * Calls to this function are generated by the event! macro
+     * Calls to this function are generated by the event! macro. element is the
+     * element the event is handled for and defaults to event.target. modifiers
+     * are the event modifiers given to event!, like "prevent,key=Enter"
      */
-    PARO.emitEvent = (event_id, event) => {
+    PARO.emitEvent = (event_id, event, element, modifiers) => {
         PARO = window.__PARO__;
-        if (PARO.websocket) {
+        // inline handlers are called with the element they are defined on as currentTarget
+        var handlerElement = element || event.currentTarget;
+        modifiers = parseModifiers(modifiers);
+        if (rendering && (event.type == "blur" || event.type == "focusout"))
+            return;
+        if (!applyModifiers(event_id, event, handlerElement, modifiers))
+            return;
+        if (PARO.websocket && modifiers.upload) {
+            var input = handlerElement || event.target;
+            uploadFiles(input, event_id, Array.from(input.files || []), modifiers.maxSize, modifiers.accept).then(() => {
+                // so the same file can be selected again
+                input.value = "";
+            });
+        } else if (PARO.websocket) {
             if (PARO.logging)
                 PARO.logger.info("[paro emit event] emitting event '" + event_id + "'", event);
+            // read now, currentTarget is gone once the event was dispatched
+            var pointer = modifiers.canvas ? canvasPointer(handlerElement || event.target, event) : undefined;
             function get_value_and_emit() {
                 var value = undefined;
-                var target = event.target;
+                var target = element || event.target;
                 var cssPath = getCssPath(target);
                 var startPosition = target.selectionStart;
                 var endPosition = target.selectionEnd;
                 if (target) {
                     value = target.value;
-                    if (target.hasAttribute("contenteditable"))
-                        value = field.innerHTML;
+                    if (pointer !== undefined)
+                        value = pointer;
+                    else if (target.hasAttribute("contenteditable"))
+                        value = target.innerHTML;
                     else if (target.matches('[type="checkbox"]'))
                         value = target.checked;
                 }
                 if (PARO.onEmitEventHandler)
                     PARO.onEmitEventHandler(event_id, event);
-                PARO.websocket.send(event_id + "__PARO__" + value);    
-                setTimeout(() => {
-                    debugger
-                    var element = document.querySelector(cssPath);
-                    if (element) {
-                        element.focus();
-                        if (startPosition) {
-                            element.selectionStart = startPosition;
-                            element.endPosition = endPosition;
-                        }
-                    }
-                }, 10);
+                PARO.websocket.send(event_id + "__PARO__" + value);
+                if (target)
+                    target.paroSentValue = value;
+                markPending(handlerElement || target);
+                clientCommands.push({
+                    command: "restoreFocus",
+                    selector: cssPath,
+                    selectionStart: startPosition,
+                    selectionEnd: endPosition
+                });
             }
 
-            if (event.type == "input")
+            if (modifiers.debounce !== undefined) {
+                var debounced = handlerElement || event.target;
+                clearTimeout(debounced.paroDebounce);
+                debounced.paroDebounce = setTimeout(get_value_and_emit, Number(modifiers.debounce));
+            } else if (modifiers.throttle !== undefined) {
+                // at most one event per interval, the last one of the interval with its value at that time
+                var throttled = handlerElement || event.target;
+                throttled.paroThrottle = throttled.paroThrottle || {};
+                var throttle = throttled.paroThrottle[event_id];
+                if (throttle) {
+                    throttle.emit = get_value_and_emit;
+                } else {
+                    throttle = throttled.paroThrottle[event_id] = { emit: get_value_and_emit };
+                    setTimeout(() => {
+                        delete throttled.paroThrottle[event_id];
+                        throttle.emit();
+                    }, Number(modifiers.throttle));
+                }
+            } else if (event.type == "input")
                 get_value_and_emit();
             else
-                // we often want to react to each button press for example for validation.
+                // we often want to react to each key press for example for validation.
                 // oninput is triggered before value is updated, so we push a callback
                 // to the end of the executions stack, to be called, once value is set.
                 setTimeout(get_value_and_emit, 0);
@@ -172,4 +1272,15 @@
         }
     };
 
+
+    // configuration via data attributes on the script tag, for apps that do not allow inline scripts
+    if (paroScript) {
+        if (paroScript.hasAttribute("data-paro-websocket-url"))
+            PARO.websocketUrl = paroScript.getAttribute("data-paro-websocket-url");
+        if (paroScript.hasAttribute("data-paro-base-element-id"))
+            PARO.baseElementId = paroScript.getAttribute("data-paro-base-element-id");
+        if (paroScript.hasAttribute("data-paro-initialize"))
+            PARO.initialize();
+    }
+
 })();
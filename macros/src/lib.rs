@@ -0,0 +1,398 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, quote_spanned};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, parse_quote, Expr, ExprClosure, Ident, LitStr, Pat, PatType, Token};
+
+
+/**
+ * Creates a mew event / callback that you can reference in your html.
+ * The event / callback is executed inside your tauri app / the server
+ * and event! returns a minimal javascript call to tell pâro which
+ * event / callback should be triggered on the server side. What you
+ * write is pure rust, pâro handles javscript.
+ *
+ * Example usage without maud templates
+ * ```ignore
+ * let html = format!(
+ *     r#"<button onclick="{}">
+ *         counter: {}
+ *     </button>"#,
+ *     event!(paro_app, (move |state: &mut ApplicationState, _| { // ApplicationState beeing whatever struct you use, here ParoApp<ApplicationState>
+ *         // this is executed here in tauri and not in the gui client application
+ *         state.current_count += 1;
+ *         println!("first number of state.numbers updated to: {}", state.current_count);
+ *     })),
+ *     paro_app.read().unwrap().state.current_count
+ * );
+ * ```
+ *
+ * Example usage with maud templates:
+ * ```ignore
+ * let maud_template = html! {
+ *     button onclick=({
+ *         event!(paro_app, (move |state: &mut ApplicationState, _| {
+ *             // this is executed here in tauri and not in the gui client application
+ *             state.current_count += 1;
+ *             println!("first number of state.numbers updated to: {}", state.current_count);
+ *         }))
+ *     }) { "counter:" (paro_app.read().unwrap().state.current_count) }
+ * };
+ * let html = maud_template.into_string();
+ * ```
+ *
+ * If you annotate the type of the value, pâro parses the value for you. Any
+ * type implementing `FromParoValue` can be used. If parsing fails, your
+ * callback is not called and the error goes to the handler set via
+ * `ParoApp::set_error_handler`. Without annotation the value is an
+ * `Option<String>`. The type of the state can be left out, it is taken
+ * from the `ParoApp` you pass in:
+ * ```ignore
+ * input type="number" onchange=({
+ *     event!(paro_app, |state, value: u64| state.current_count = value)
+ * }) {}
+ * ```
+ *
+ * Callbacks can take the `ParoContext` as third argument, for example to only
+ * re-render a region instead of the whole page:
+ * ```ignore
+ * event!(paro_app, |state, value: String, ctx| {
+ *     state.search_term = value;
+ *     ctx.mark_dirty("results");
+ * })
+ * ```
+ *
+ * Modifiers after the callback are applied by paro.js before the event is send:
+ * - `prevent` calls `event.preventDefault()`, for example to keep a form from reloading the page
+ * - `stop` calls `event.stopPropagation()`, so callbacks of parent elements are not triggered
+ * - `self` only triggers if the event happened on the element itself and not on a child
+ * - `once` only triggers the first time, the server drops repeated calls as well
+ * - `key = "Enter"` only triggers for that key, see `KeyboardEvent.key`
+ * - `ctrl`, `shift`, `alt`, `meta` only trigger if that key is pressed as well
+ * - `canvas` sends the position of a pointer event on a canvas, see `CanvasPointer`
+ * - `pressed` only triggers while a mouse button is pressed or the pointer touches, for pointer events
+ * - `debounce = "200"` waits until the event did not happen for that many milliseconds
+ * - `throttle = "50"` triggers at most once per that many milliseconds, with the last event of that time.
+ *   For events that happen many times per second, like `pointermove`
+ * ```ignore
+ * input onkeydown=(event!(paro_app, |state, value: String| state.search(value), key = "Enter", prevent)) {}
+ * ```
+ */
+#[proc_macro]
+pub fn event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as EventInput);
+    match expand_event(input, false) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+
+/**
+ * Like event!, but the callback can only be called once and may consume what it
+ * captured, as it is a `FnOnce`. Use it for things like saving or deleting, that
+ * must not happen twice. Further calls, for example from a double click, report
+ * `ParoError::AlreadyConsumed` to your error handler. The `once` modifier is
+ * added for you, so paro.js does not send the event twice either.
+ * ```ignore
+ * let employee = state.edit_state.employee.clone();
+ * button onclick=(event_once!(paro_app, move |state, _| state.employees.push(employee))) { "save" }
+ * ```
+ */
+#[proc_macro]
+pub fn event_once(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as EventInput);
+    match expand_event(input, true) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+
+/**
+ * Like event!, but the callback is async. Instead of the state it receives a
+ * `ParoAsyncContext`, which can wait for the user to answer a dialog and
+ * change the state afterwards. Modifiers work just like they do for event!.
+ * ```ignore
+ * button onclick=(event_async!(paro_app, move |ctx: ParoAsyncContext<ApplicationState>, _| async move {
+ *     if ctx.confirm("Delete all members?").await {
+ *         ctx.update(|state, _ctx| state.employees.clear()).await;
+ *     }
+ * })) { "delete all" }
+ * ```
+ */
+#[proc_macro]
+pub fn event_async(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as EventInput);
+    match expand_async_event(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+
+struct EventInput {
+    paro_app: Expr,
+    callback: Expr,
+    modifiers: Vec<Modifier>,
+}
+
+impl Parse for EventInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let paro_app = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let callback = input.parse()?;
+        let mut modifiers = vec![];
+        while input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            modifiers.push(input.parse()?);
+        }
+        Ok(EventInput { paro_app, callback, modifiers })
+    }
+}
+
+
+/**
+ * An event modifier like `prevent` or `key = "Enter"`
+ */
+struct Modifier {
+    name: Ident,
+    value: Option<LitStr>,
+}
+
+const FLAG_MODIFIERS: [&str; 10] = ["prevent", "stop", "self", "once", "ctrl", "shift", "alt", "meta", "canvas", "pressed"];
+const VALUE_MODIFIERS: [&str; 3] = ["key", "debounce", "throttle"];
+
+impl Parse for Modifier {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // any ident, as self is a keyword
+        let name = Ident::parse_any(input)?;
+        let name_string = name.to_string();
+        if FLAG_MODIFIERS.contains(&name_string.as_str()) {
+            return Ok(Modifier { name, value: None });
+        }
+        if VALUE_MODIFIERS.contains(&name_string.as_str()) {
+            input.parse::<Token![=]>()?;
+            let value: LitStr = input.parse()?;
+            if name_string != "key" && value.value().parse::<u32>().is_err() {
+                return Err(syn::Error::new(
+                    value.span(),
+                    format!("[paro] `{}` expects milliseconds, like {} = \"50\"", name_string, name_string),
+                ));
+            }
+            return Ok(Modifier { name, value: Some(value) });
+        }
+        Err(syn::Error::new(
+            name.span(),
+            format!(
+                "[paro] unknown event modifier `{}`, expected one of {}, {}",
+                name_string,
+                FLAG_MODIFIERS.join(", "),
+                VALUE_MODIFIERS.iter().map(|name| format!("{} = \"...\"", name)).collect::<Vec<String>>().join(", ")
+            ),
+        ))
+    }
+}
+
+/**
+ * Encodes modifiers for paro.js like `prevent,key=Enter`. Values are percent
+ * encoded, so they can neither break the format nor the surrounding html.
+ */
+fn encode_modifiers(modifiers: &[Modifier]) -> String {
+    modifiers.iter()
+        .map(|modifier| match &modifier.value {
+            None => modifier.name.to_string(),
+            Some(value) => format!("{}={}", modifier.name, percent_encode(&value.value())),
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+// compatible with decodeURIComponent
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' || byte == b'.' {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+
+fn expand_event(mut input: EventInput, once: bool) -> syn::Result<TokenStream2> {
+    let paro_app = input.paro_app;
+    // once is enforced by the server as well, in case the client sends the event again
+    let one_shot = input.modifiers.iter().any(|modifier| modifier.name == "once");
+    if once && !one_shot {
+        input.modifiers.push(Modifier { name: Ident::new("once", Span::call_site()), value: None });
+    }
+    let modifiers = encode_modifiers(&input.modifiers);
+    let (callback, with_context) = match unwrap_parens(input.callback) {
+        Expr::Closure(closure) => {
+            let closure = annotate_value(closure)?;
+            let with_context = closure.inputs.len() == 3;
+            (Expr::Closure(closure), with_context)
+        },
+        // a function or a variable holding a closure, rustc checks the signature
+        other => (other, false),
+    };
+    let register_fn = match (once, with_context) {
+        (false, false) => quote!(register),
+        (false, true) => quote!(register_with_context),
+        (true, false) => quote!(register_once),
+        (true, true) => quote!(register_once_with_context),
+    };
+    // FnOnce callbacks are always one-shot
+    let one_shot = if once { quote!() } else { quote!(#one_shot,) };
+    Ok(expand_register(paro_app, register_fn, one_shot, callback, &modifiers))
+}
+
+fn expand_async_event(input: EventInput) -> syn::Result<TokenStream2> {
+    let one_shot = input.modifiers.iter().any(|modifier| modifier.name == "once");
+    let modifiers = encode_modifiers(&input.modifiers);
+    let callback = match unwrap_parens(input.callback) {
+        Expr::Closure(mut closure) => {
+            if closure.inputs.len() != 2 {
+                return Err(syn::Error::new(
+                    closure.inputs.span(),
+                    format!(
+                        "[paro] async callbacks take two arguments, the context and the value, \
+                         like `|ctx: ParoAsyncContext<State>, value: u64|`, but this one takes {}",
+                        closure.inputs.len()
+                    ),
+                ));
+            }
+            annotate_untyped(&mut closure.inputs[1]);
+            Expr::Closure(closure)
+        },
+        other => other,
+    };
+    Ok(expand_register(input.paro_app, quote!(register_async), quote!(#one_shot,), callback, &modifiers))
+}
+
+/**
+ * Registers the callback under a new id and returns what paro.js needs to call it
+ */
+fn expand_register(paro_app: Expr, register_fn: TokenStream2, one_shot: TokenStream2, callback: Expr, modifiers: &str) -> TokenStream2 {
+    // locals are mixed site, so they cannot clash with anything the callback captures
+    let callback_id = format_ident!("callback_id", span = Span::mixed_site());
+    let my_paro = format_ident!("my_paro", span = Span::mixed_site());
+
+    // spanned, so signature errors point at the callback instead of the macro
+    let register = quote_spanned! {callback.span()=>
+        ::paro_rs::__private::#register_fn(&#my_paro, ::std::clone::Clone::clone(&#callback_id), #one_shot #callback)
+    };
+
+    quote! {
+        {
+            let #callback_id = ::std::string::ToString::to_string(&::paro_rs::Uuid::new_v4());
+            let #my_paro = (#paro_app).clone();
+            #register;
+            ::paro_rs::__private::emit_event_call(&#callback_id, #modifiers)
+        }
+    }
+}
+
+fn unwrap_parens(expr: Expr) -> Expr {
+    match expr {
+        Expr::Paren(paren) => unwrap_parens(*paren.expr),
+        other => other,
+    }
+}
+
+/**
+ * Checks that the closure takes the state, the value and optionally the context
+ * and annotates the value as Option<String> if the user did not specify a type.
+ */
+fn annotate_value(mut closure: ExprClosure) -> syn::Result<ExprClosure> {
+    if closure.inputs.len() != 2 && closure.inputs.len() != 3 {
+        return Err(syn::Error::new(
+            closure.inputs.span(),
+            format!(
+                "[paro] callbacks take two or three arguments, the state, the value and optionally the context, \
+                 like `|state: &mut State, value: u64, ctx: &mut ParoContext<State>|`, but this one takes {}",
+                closure.inputs.len()
+            ),
+        ));
+    }
+    annotate_untyped(&mut closure.inputs[1]);
+    Ok(closure)
+}
+
+// values without a type are the raw value
+fn annotate_untyped(value: &mut Pat) {
+    if !matches!(value, Pat::Type(_)) {
+        let untyped = value.clone();
+        *value = Pat::Type(PatType {
+            attrs: vec![],
+            pat: Box::new(untyped),
+            colon_token: Default::default(),
+            ty: Box::new(parse_quote!(::std::option::Option<::std::string::String>)),
+        });
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modifiers(input: &str) -> Vec<Modifier> {
+        syn::parse_str::<EventInput>(&format!("paro_app, |state, _| {{}}, {}", input)).unwrap().modifiers
+    }
+
+    #[test]
+    fn encodes_flags_and_values() {
+        assert_eq!(encode_modifiers(&modifiers("")), "");
+        assert_eq!(encode_modifiers(&modifiers("prevent, stop, self")), "prevent,stop,self");
+        assert_eq!(encode_modifiers(&modifiers(r#"key = "Enter", ctrl"#)), "key=Enter,ctrl");
+        assert_eq!(encode_modifiers(&modifiers(r#"debounce = "200","#)), "debounce=200");
+        assert_eq!(encode_modifiers(&modifiers(r#"canvas, pressed, throttle = "16""#)), "canvas,pressed,throttle=16");
+    }
+
+    #[test]
+    fn percent_encodes_values() {
+        assert_eq!(encode_modifiers(&modifiers(r#"key = " ""#)), "key=%20");
+        assert_eq!(encode_modifiers(&modifiers(r#"key = ",""#)), "key=%2C");
+        assert_eq!(encode_modifiers(&modifiers(r#"key = "=""#)), "key=%3D");
+        assert_eq!(percent_encode("a\"<'`ä"), "a%22%3C%27%60%C3%A4");
+        assert_eq!(percent_encode("Arrow-Up_1.x"), "Arrow-Up_1.x");
+    }
+
+    #[test]
+    fn event_once_adds_the_once_modifier() {
+        let input = syn::parse_str::<EventInput>(r#"paro_app, |state, _| {}, key = "Enter""#).unwrap();
+        let expanded = expand_event(input, true).unwrap().to_string();
+        assert!(expanded.contains(r#""key=Enter,once""#), "{}", expanded);
+
+        let input = syn::parse_str::<EventInput>("paro_app, |state, _| {}, once").unwrap();
+        let expanded = expand_event(input, true).unwrap().to_string();
+        assert!(expanded.contains(r#""once""#) && !expanded.contains("once,once"), "{}", expanded);
+    }
+
+    #[test]
+    fn rejects_unknown_modifiers() {
+        assert!(syn::parse_str::<EventInput>("paro_app, |state, _| {}, passive").is_err());
+        assert!(syn::parse_str::<EventInput>("paro_app, |state, _| {}, key").is_err());
+        assert!(syn::parse_str::<EventInput>(r#"paro_app, |state, _| {}, pressed = "1""#).is_err());
+    }
+
+    #[test]
+    fn canvas_events_can_be_filtered_and_throttled() {
+        let input = syn::parse_str::<EventInput>(
+            r#"paro_app, |state, pointer: CanvasPointer| {}, canvas, pressed, throttle = "16""#
+        ).unwrap();
+        let expanded = expand_event(input, false).unwrap().to_string();
+        assert!(expanded.contains(r#""canvas,pressed,throttle=16""#), "{}", expanded);
+        assert!(syn::parse_str::<EventInput>(r#"paro_app, |state, _| {}, throttle = "fast""#).is_err());
+        assert!(syn::parse_str::<EventInput>(r#"paro_app, |state, _| {}, debounce = "-1""#).is_err());
+    }
+}
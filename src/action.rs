@@ -0,0 +1,69 @@
+use crate::{ParoCommand, ParoContext};
+
+
+/**
+ * What should happen after a callback ran. Callbacks can return an action
+ * instead of `()`:
+ *
+ * ```ignore
+ * event!(paro_app, |state, value: String| {
+ *     state.log.push(value);
+ *     ParoAction::NoRender
+ * })
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParoAction {
+    /// Render the page, or only the regions marked dirty. What happens without an action.
+    Render,
+    /// Do not render anything, for callbacks that change nothing visible
+    NoRender,
+    /// Only re-render the region with the given name, see `ParoContext::mark_dirty`
+    RenderRegion(String),
+    /// Show a short message to the user, see `ParoContext::toast` for other levels than `Level::Info`
+    Toast(String),
+    /// Focus the element matching the css selector after rendering
+    Focus(String),
+    /// Let paro.js execute the command after rendering
+    Command(ParoCommand),
+}
+
+
+/**
+ * Everything a callback can return. Implemented for `()`, `ParoAction` and
+ * `Option` or `Vec` of actions.
+ */
+#[diagnostic::on_unimplemented(
+    message = "[paro] callbacks have to return `()` or a `ParoAction`, not `{Self}`",
+    label = "callback returns `{Self}`",
+    note = "end the callback with `;` if you do not want to return anything"
+)]
+pub trait IntoParoAction {
+    fn apply<State: 'static>(self, context: &mut ParoContext<State>);
+}
+
+impl IntoParoAction for () {
+    fn apply<State: 'static>(self, _context: &mut ParoContext<State>) {}
+}
+
+impl IntoParoAction for ParoAction {
+    fn apply<State: 'static>(self, context: &mut ParoContext<State>) {
+        context.apply(self);
+    }
+}
+
+impl IntoParoAction for Option<ParoAction> {
+    fn apply<State: 'static>(self, context: &mut ParoContext<State>) {
+        if let Some(action) = self {
+            context.apply(action);
+        }
+    }
+}
+
+impl IntoParoAction for Vec<ParoAction> {
+    fn apply<State: 'static>(self, context: &mut ParoContext<State>) {
+        for action in self {
+            context.apply(action);
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{FromParoValue, ImageHandle, ParoClient, ParoMessage};
+use crate::protocol::ServerMessage;
+
+
+/**
+ * Draws on a `<canvas data-paro-canvas="<name>">` without rendering html, for
+ * charts or diagrams that change many times per second. Get one via
+ * `ParoContext::canvas` or `ParoApp::canvas`. It can be cloned and moved to
+ * another thread or task that keeps drawing.
+ *
+ * ```ignore
+ * let canvas = paro_app.read().unwrap().canvas("chart");
+ * canvas.draw(|frame| {
+ *     frame.fill_style("#0d6efd");
+ *     for (index, value) in values.iter().enumerate() {
+ *         frame.fill_rect(index as f64 * 10.0, 100.0 - value, 8.0, *value);
+ *     }
+ * });
+ * ```
+ *
+ * Every frame replaces the whole drawing. paro.js remembers the last frame of
+ * each canvas and draws it again if the canvas was rendered again. Frames are
+ * send via the messages of `ParoApp::connect`, so your websocket loop has to
+ * forward those.
+ */
+#[derive(Clone)]
+pub struct ParoCanvas {
+    name: String,
+    client: ParoClient,
+}
+
+impl fmt::Debug for ParoCanvas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParoCanvas").field("name", &self.name).finish()
+    }
+}
+
+impl ParoCanvas {
+    pub(crate) fn new(name: &str, client: ParoClient) -> ParoCanvas {
+        ParoCanvas {
+            name: name.to_owned(),
+            client,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /**
+     * Collects the drawing commands of `draw` and sends them as one frame.
+     * Returns false if paro.js is not connected.
+     */
+    pub fn draw<Draw>(&self, draw: Draw) -> bool
+    where
+        Draw: FnOnce(&mut CanvasFrame),
+    {
+        let mut frame = CanvasFrame {
+            canvas: self.name.clone(),
+            ops: vec![],
+        };
+        draw(&mut frame);
+        self.client.send(ParoMessage::Text(ServerMessage {
+            canvas: vec![frame],
+            ..ServerMessage::default()
+        }.encode()))
+    }
+}
+
+
+/**
+ * The drawing commands of one frame, named after the methods and properties
+ * of `CanvasRenderingContext2D`. Coordinates are in canvas pixels, see the
+ * `width` and `height` attributes of the canvas.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CanvasFrame {
+    canvas: String,
+    // [name, arguments..], a method call or a property to set
+    ops: Vec<Value>,
+}
+
+impl CanvasFrame {
+    fn op(&mut self, op: Value) -> &mut CanvasFrame {
+        self.ops.push(op);
+        self
+    }
+
+    pub fn fill_style(&mut self, style: &str) -> &mut CanvasFrame {
+        self.op(json!(["fillStyle", style]))
+    }
+
+    pub fn stroke_style(&mut self, style: &str) -> &mut CanvasFrame {
+        self.op(json!(["strokeStyle", style]))
+    }
+
+    pub fn line_width(&mut self, width: f64) -> &mut CanvasFrame {
+        self.op(json!(["lineWidth", width]))
+    }
+
+    /// a css font like `12px sans-serif`
+    pub fn font(&mut self, font: &str) -> &mut CanvasFrame {
+        self.op(json!(["font", font]))
+    }
+
+    pub fn global_alpha(&mut self, alpha: f64) -> &mut CanvasFrame {
+        self.op(json!(["globalAlpha", alpha]))
+    }
+
+    pub fn save(&mut self) -> &mut CanvasFrame {
+        self.op(json!(["save"]))
+    }
+
+    pub fn restore(&mut self) -> &mut CanvasFrame {
+        self.op(json!(["restore"]))
+    }
+
+    pub fn translate(&mut self, x: f64, y: f64) -> &mut CanvasFrame {
+        self.op(json!(["translate", x, y]))
+    }
+
+    pub fn scale(&mut self, x: f64, y: f64) -> &mut CanvasFrame {
+        self.op(json!(["scale", x, y]))
+    }
+
+    pub fn rotate(&mut self, angle: f64) -> &mut CanvasFrame {
+        self.op(json!(["rotate", angle]))
+    }
+
+    pub fn begin_path(&mut self) -> &mut CanvasFrame {
+        self.op(json!(["beginPath"]))
+    }
+
+    pub fn close_path(&mut self) -> &mut CanvasFrame {
+        self.op(json!(["closePath"]))
+    }
+
+    pub fn move_to(&mut self, x: f64, y: f64) -> &mut CanvasFrame {
+        self.op(json!(["moveTo", x, y]))
+    }
+
+    pub fn line_to(&mut self, x: f64, y: f64) -> &mut CanvasFrame {
+        self.op(json!(["lineTo", x, y]))
+    }
+
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) -> &mut CanvasFrame {
+        self.op(json!(["rect", x, y, width, height]))
+    }
+
+    /// angles in radians
+    pub fn arc(&mut self, x: f64, y: f64, radius: f64, start_angle: f64, end_angle: f64) -> &mut CanvasFrame {
+        self.op(json!(["arc", x, y, radius, start_angle, end_angle]))
+    }
+
+    /// fills the current path
+    pub fn fill(&mut self) -> &mut CanvasFrame {
+        self.op(json!(["fill"]))
+    }
+
+    /// strokes the current path
+    pub fn stroke(&mut self) -> &mut CanvasFrame {
+        self.op(json!(["stroke"]))
+    }
+
+    pub fn fill_rect(&mut self, x: f64, y: f64, width: f64, height: f64) -> &mut CanvasFrame {
+        self.op(json!(["fillRect", x, y, width, height]))
+    }
+
+    pub fn stroke_rect(&mut self, x: f64, y: f64, width: f64, height: f64) -> &mut CanvasFrame {
+        self.op(json!(["strokeRect", x, y, width, height]))
+    }
+
+    pub fn clear_rect(&mut self, x: f64, y: f64, width: f64, height: f64) -> &mut CanvasFrame {
+        self.op(json!(["clearRect", x, y, width, height]))
+    }
+
+    pub fn fill_text(&mut self, text: &str, x: f64, y: f64) -> &mut CanvasFrame {
+        self.op(json!(["fillText", text, x, y]))
+    }
+
+    /**
+     * Draws an image registered via `ParoImages`. It is left out until paro.js
+     * received the image, the frame is drawn again once it did.
+     */
+    pub fn draw_image(&mut self, image: &ImageHandle, x: f64, y: f64, width: f64, height: f64) -> &mut CanvasFrame {
+        self.op(json!(["image", image.as_str(), x, y, width, height]))
+    }
+}
+
+
+/**
+ * A pointer event on a canvas, send as value of callbacks with the `canvas`
+ * modifier. Coordinates are in canvas pixels, even if the canvas is scaled
+ * via css.
+ *
+ * ```ignore
+ * canvas data-paro-canvas="sketch" width="400" height="200"
+ *     onpointerdown=(event!(paro_app, |state, pointer: CanvasPointer| state.add_point(pointer.x, pointer.y), canvas)) {}
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CanvasPointer {
+    /// the type of the event, like `pointerdown` or `pointermove`
+    pub event: String,
+    pub x: f64,
+    pub y: f64,
+    /// see `MouseEvent.button`
+    pub button: i16,
+    /// see `MouseEvent.buttons`, 0 if no button is pressed
+    pub buttons: u16,
+}
+
+impl FromParoValue for CanvasPointer {
+    fn from_paro_value(value: Option<String>) -> Result<Self, String> {
+        match value {
+            None => Err("no value".to_owned()),
+            Some(value) => serde_json::from_str(&value)
+                .map_err(|error| format!("not a canvas pointer event, did you forget the canvas modifier? {}", error)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::MESSAGE_PREFIX;
+
+    // the message paro.js receives, as text and parsed
+    fn draw(name: &str, draw: impl FnOnce(&mut CanvasFrame)) -> (String, Value) {
+        let client = ParoClient::default();
+        let (mut receiver, _connection) = client.connect();
+        assert!(ParoCanvas::new(name, client.clone()).draw(draw));
+        match receiver.try_recv() {
+            Ok(ParoMessage::Text(text)) => {
+                let message = serde_json::from_str(text.trim_start_matches(MESSAGE_PREFIX)).unwrap();
+                (text, message)
+            },
+            other => panic!("expected a frame, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sends_the_ops_of_a_frame_in_order() {
+        let (_, message) = draw("chart", |frame| {
+            frame.fill_style("#0d6efd")
+                .begin_path()
+                .move_to(0.0, 1.5)
+                .line_to(10.0, 20.0)
+                .stroke()
+                .fill_text("max", 5.0, 5.0);
+        });
+        assert_eq!(message["canvas"], json!([{
+            "canvas": "chart",
+            "ops": [
+                ["fillStyle", "#0d6efd"],
+                ["beginPath"],
+                ["moveTo", 0.0, 1.5],
+                ["lineTo", 10.0, 20.0],
+                ["stroke"],
+                ["fillText", "max", 5.0, 5.0],
+            ],
+        }]));
+    }
+
+    #[test]
+    fn escapes_canvas_names_and_texts() {
+        let name = r#"chart" onload="alert(1)"#;
+        let (text, message) = draw(name, |frame| {
+            frame.fill_text("</script>", 0.0, 0.0);
+        });
+        assert!(text.contains(r#""canvas":"chart\" onload=\"alert(1)""#), "{}", text);
+        assert_eq!(message["canvas"][0]["canvas"], name);
+        assert_eq!(message["canvas"][0]["ops"][0], json!(["fillText", "</script>", 0.0, 0.0]));
+    }
+
+    #[test]
+    fn frames_are_not_sent_without_connection() {
+        assert!(!ParoCanvas::new("chart", ParoClient::default()).draw(|frame| {
+            frame.clear_rect(0.0, 0.0, 10.0, 10.0);
+        }));
+    }
+
+    #[test]
+    fn parses_pointer_events_of_the_canvas_modifier() {
+        let pointer = CanvasPointer::from_paro_value(Some(
+            r#"{"event":"pointermove","x":12.5,"y":3,"button":-1,"buttons":1}"#.to_owned()
+        ));
+        assert_eq!(pointer, Ok(CanvasPointer { event: "pointermove".to_owned(), x: 12.5, y: 3.0, button: -1, buttons: 1 }));
+        assert!(CanvasPointer::from_paro_value(Some("on".to_owned())).unwrap_err().contains("canvas modifier"));
+        assert!(CanvasPointer::from_paro_value(None).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures_channel::oneshot;
+use serde_json::Value;
+
+use crate::{ParoError, ParoQuery};
+use crate::protocol::ServerMessage;
+
+
+/**
+ * Messages pâro sends to paro.js on its own, like queries. Forward them to
+ * the websocket, see `ParoApp::connect`.
+ */
+pub type ParoReceiver = UnboundedReceiver<ParoMessage>;
+
+
+/**
+ * Identifies a connection started by `ParoApp::connect`, so
+ * `ParoApp::disconnect` only ends that one and not a newer connection
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnectionToken(u64);
+
+
+/**
+ * A message for paro.js, to be send as text or binary websocket message
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParoMessage {
+    Text(String),
+    /// images, see `ParoImages`
+    Binary(Vec<u8>),
+    /// Not for paro.js: an async callback continued on its own, for example
+    /// after a timer, send the result of `render_update` instead
+    Render,
+}
+
+
+/**
+ * Everything that can go wrong while asking paro.js something
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryError {
+    /// paro.js did not answer in time
+    Timeout,
+    /// There is no connection to paro.js, see `ParoApp::connect`, or it was lost
+    Disconnected,
+    /// paro.js could not answer the query, for example because it does not know it
+    Client(String),
+    /// The answer could not be parsed into the reply type of the query
+    InvalidReply(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Timeout => write!(f, "[paro] query timed out"),
+            QueryError::Disconnected => write!(f, "[paro] query failed, paro.js is not connected"),
+            QueryError::Client(message) => write!(f, "[paro] paro.js could not answer query: {}", message),
+            QueryError::InvalidReply(message) => write!(f, "[paro] invalid reply to query: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+
+type PendingReply = oneshot::Sender<Result<Value, QueryError>>;
+
+#[derive(Default)]
+struct Connection {
+    sender: Option<UnboundedSender<ParoMessage>>,
+    // the id of the current connection, 0 before the first one
+    id: u64,
+    next_query_id: u64,
+    // by query id, with the time the query times out
+    pending: HashMap<u64, (Instant, PendingReply)>,
+    timer_running: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    connection: Mutex<Connection>,
+    // wakes the timer thread when a query was sent
+    timer: Condvar,
+    // errors of those that cannot reach the ParoApp, like ParoStorage, see ParoApp::set_error_handler
+    errors: Mutex<Vec<ParoError>>,
+}
+
+
+/**
+ * Asks paro.js about things only the client knows, like the size of an element
+ * or the text the user selected. It is cheap to clone and can be moved into
+ * async tasks, get it via `ParoContext::client` or `ParoApp::client`.
+ *
+ * ```ignore
+ * event!(paro_app, |state, _, ctx| {
+ *     let client = ctx.client();
+ *     tokio::spawn(async move {
+ *         match client.query(ElementSize::new("#chart")).await {
+ *             Ok(Some(rect)) => println!("chart is {}px wide", rect.width),
+ *             Ok(None) => println!("there is no chart"),
+ *             Err(error) => println!("{}", error),
+ *         }
+ *     });
+ * })
+ * ```
+ *
+ * Never block on a query while holding a lock on your `ParoApp`, the answer
+ * arrives through `ParoApp::call`, which needs that lock.
+ */
+#[derive(Clone, Default)]
+pub struct ParoClient {
+    shared: Arc<Shared>,
+}
+
+impl fmt::Debug for ParoClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let connection = self.shared.connection.lock().unwrap();
+        f.debug_struct("ParoClient")
+            .field("connected", &connection.sender.is_some())
+            .field("pending_queries", &connection.pending.len())
+            .finish()
+    }
+}
+
+impl ParoClient {
+    /// How long `query` waits for paro.js to answer
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /**
+     * Sends the query to paro.js and waits for the answer for at most
+     * `DEFAULT_TIMEOUT`
+     */
+    pub async fn query<Query: ParoQuery>(&self, query: Query) -> Result<Query::Reply, QueryError> {
+        self.query_timeout(query, ParoClient::DEFAULT_TIMEOUT).await
+    }
+
+    /**
+     * Sends the query to paro.js and waits for the answer for at most `timeout`
+     */
+    pub async fn query_timeout<Query: ParoQuery>(&self, query: Query, timeout: Duration) -> Result<Query::Reply, QueryError> {
+        let mut message = serde_json::to_value(&query).expect("[paro] could not serialize query");
+        let (reply_sender, reply_receiver) = oneshot::channel();
+        {
+            let mut connection = self.shared.connection.lock().unwrap();
+            connection.next_query_id += 1;
+            let id = connection.next_query_id;
+            if let Value::Object(fields) = &mut message {
+                fields.insert("id".to_owned(), Value::from(id));
+            }
+            let encoded = ServerMessage {
+                queries: vec![message],
+                ..ServerMessage::default()
+            }.encode();
+            let sent = match &connection.sender {
+                Some(sender) => sender.unbounded_send(ParoMessage::Text(encoded)).is_ok(),
+                None => false,
+            };
+            if !sent {
+                return Err(QueryError::Disconnected);
+            }
+            connection.pending.insert(id, (Instant::now() + timeout, reply_sender));
+            // a thread instead of a timer, so pâro does not depend on a specific async runtime
+            if connection.timer_running {
+                self.shared.timer.notify_one();
+            } else {
+                connection.timer_running = true;
+                let shared = Arc::downgrade(&self.shared);
+                std::thread::spawn(move || time_out_queries(shared));
+            }
+        }
+
+        match reply_receiver.await {
+            Ok(Ok(value)) => serde_json::from_value(value)
+                .map_err(|error| QueryError::InvalidReply(error.to_string())),
+            Ok(Err(error)) => Err(error),
+            Err(_canceled) => Err(QueryError::Disconnected),
+        }
+    }
+
+    /**
+     * Starts a new connection. Queries still waiting for an answer from the
+     * previous connection fail with `QueryError::Disconnected`.
+     */
+    pub(crate) fn connect(&self) -> (ParoReceiver, ConnectionToken) {
+        let (sender, receiver) = unbounded();
+        let mut connection = self.shared.connection.lock().unwrap();
+        connection.sender = Some(sender);
+        connection.id += 1;
+        connection.pending.clear();
+        (receiver, ConnectionToken(connection.id))
+    }
+
+    /**
+     * Whether `connection` is the current connection and was not ended yet
+     */
+    pub(crate) fn is_connected(&self, connection: ConnectionToken) -> bool {
+        let current = self.shared.connection.lock().unwrap();
+        current.id == connection.0 && current.sender.is_some()
+    }
+
+    /**
+     * Ends the connection. Queries still waiting for an answer fail with
+     * `QueryError::Disconnected`.
+     */
+    pub(crate) fn disconnect(&self) {
+        let mut connection = self.shared.connection.lock().unwrap();
+        connection.sender = None;
+        connection.pending.clear();
+    }
+
+    /**
+     * Sends the message to paro.js. false if it is not connected.
+     */
+    pub(crate) fn send(&self, message: ParoMessage) -> bool {
+        match &self.shared.connection.lock().unwrap().sender {
+            Some(sender) => sender.unbounded_send(message).is_ok(),
+            None => false,
+        }
+    }
+
+    /**
+     * Reports the error to the error handler of the `ParoApp`, once it handles
+     * the next event or renders
+     */
+    pub(crate) fn report(&self, error: ParoError) {
+        self.shared.errors.lock().unwrap().push(error);
+    }
+
+    pub(crate) fn take_errors(&self) -> Vec<ParoError> {
+        std::mem::take(&mut *self.shared.errors.lock().unwrap())
+    }
+
+    /**
+     * Passes the answer of paro.js to whoever waits for it. Answers to queries
+     * that already timed out are dropped.
+     */
+    pub(crate) fn resolve(&self, id: u64, reply: Result<Value, QueryError>) {
+        let pending = self.shared.connection.lock().unwrap().pending.remove(&id);
+        if let Some((_timeout, reply_sender)) = pending {
+            let _ = reply_sender.send(reply);
+        }
+    }
+}
+
+
+/**
+ * Lets queries fail with `QueryError::Timeout` once paro.js took too long to
+ * answer. One thread per `ParoClient`, it ends once the client is dropped.
+ */
+fn time_out_queries(client: Weak<Shared>) {
+    // wakes up now and then without queries, to notice the client was dropped
+    const IDLE: Duration = Duration::from_secs(10);
+    while let Some(shared) = client.upgrade() {
+        let mut connection = shared.connection.lock().unwrap();
+        let now = Instant::now();
+        let timed_out: Vec<u64> = connection.pending.iter()
+            .filter(|(_id, (timeout, _reply_sender))| *timeout <= now)
+            .map(|(id, _pending)| *id)
+            .collect();
+        for id in timed_out {
+            if let Some((_timeout, reply_sender)) = connection.pending.remove(&id) {
+                let _ = reply_sender.send(Err(QueryError::Timeout));
+            }
+        }
+        let wait = connection.pending.values()
+            .map(|(timeout, _reply_sender)| timeout.saturating_duration_since(now))
+            .min()
+            .unwrap_or(IDLE);
+        drop(shared.timer.wait_timeout(connection, wait).unwrap());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+
+    use super::*;
+    use crate::TextSelection;
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<Output>(future: impl Future<Output = Output>) -> Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn queries_fail_without_connection() {
+        let client = ParoClient::default();
+        assert_eq!(block_on(client.query(TextSelection::new())), Err(QueryError::Disconnected));
+    }
+
+    #[test]
+    fn queries_time_out_and_late_replies_are_dropped() {
+        let client = ParoClient::default();
+        let (_receiver, _connection) = client.connect();
+        let slow = client.clone();
+        let fast = client.clone();
+        let slow = thread::spawn(move || block_on(slow.query_timeout(TextSelection::new(), Duration::from_millis(300))));
+        // so the slow query is query 1
+        while client.shared.connection.lock().unwrap().pending.is_empty() {
+            thread::yield_now();
+        }
+        let fast = thread::spawn(move || block_on(fast.query_timeout(TextSelection::new(), Duration::from_millis(20))));
+        assert_eq!(fast.join().unwrap(), Err(QueryError::Timeout));
+        // the first query is still waiting
+        client.resolve(1, Ok(Value::from("selected")));
+        assert_eq!(slow.join().unwrap(), Ok("selected".to_owned()));
+        // nobody waits for it anymore
+        client.resolve(2, Ok(Value::from("late")));
+    }
+}
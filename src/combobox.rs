@@ -0,0 +1,328 @@
+use crate::{event_attribute, ComponentScope, ParoComponent};
+use crate::render::{check_name, escape_html};
+
+
+/**
+ * A suggestion of a `Combobox`
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion<Item> {
+    pub label: String,
+    pub item: Item,
+}
+
+impl <Item> Suggestion<Item> {
+    pub fn new(label: &str, item: Item) -> Suggestion<Item> {
+        Suggestion {
+            label: label.to_owned(),
+            item,
+        }
+    }
+}
+
+
+/**
+ * What a `Combobox` tells its parent
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComboboxMessage<Item> {
+    /// The user typed, answer with `Combobox::set_suggestions`
+    Search(String),
+    /// The user picked a suggestion, its label is shown in the input
+    Selected(Item),
+    /// The user changed the text, so the previously selected item no longer applies
+    Cleared,
+}
+
+
+/**
+ * An input that suggests items while the user types, for picking one out of
+ * more items than a select can handle. The suggestions come from your
+ * application, as answer to `ComboboxMessage::Search`. paro.js handles
+ * moving through them with the arrow keys and keeps what the user typed
+ * while suggestions are rendered.
+ *
+ * ```ignore
+ * (PreEscaped(render_component(
+ *     paro_app,
+ *     &state.manager,
+ *     |state: &mut ApplicationState| &mut state.manager,
+ *     |state: &mut ApplicationState, message: ComboboxMessage<String>| match message {
+ *         ComboboxMessage::Search(text) => {
+ *             let suggestions = state.employees.iter()
+ *                 .filter(|employee| employee.login.contains(&text))
+ *                 .map(|employee| Suggestion::new(&employee.login, employee.id.clone()))
+ *                 .collect();
+ *             state.manager.set_suggestions(suggestions);
+ *         },
+ *         ComboboxMessage::Selected(id) => state.manager_id = Some(id),
+ *         ComboboxMessage::Cleared => state.manager_id = None,
+ *     },
+ * )))
+ * ```
+ *
+ * Style it via the `paro-combobox-*` classes, the active suggestion has the
+ * `paro-active` class.
+ */
+#[derive(Debug, Clone)]
+pub struct Combobox<Item> {
+    name: String,
+    label: String,
+    placeholder: String,
+    debounce: u32,
+    max_suggestions: usize,
+    text: String,
+    selected: Option<Item>,
+    suggestions: Vec<Suggestion<Item>>,
+    expanded: bool,
+}
+
+impl <Item: Clone> Combobox<Item> {
+    /**
+     * `name` is used for the id of the input, `label` is read by screen readers.
+     * Panics unless `name` only contains ASCII letters, digits, `-` and `_`.
+     */
+    pub fn new(name: &str, label: &str) -> Combobox<Item> {
+        check_name("combobox", name);
+        Combobox {
+            name: name.to_owned(),
+            label: label.to_owned(),
+            placeholder: "".to_owned(),
+            debounce: 150,
+            max_suggestions: 10,
+            text: "".to_owned(),
+            selected: None,
+            suggestions: vec![],
+            expanded: false,
+        }
+    }
+
+    pub fn placeholder(mut self, placeholder: &str) -> Combobox<Item> {
+        self.placeholder = placeholder.to_owned();
+        self
+    }
+
+    /// milliseconds the user has to stop typing before asking for suggestions, 150 by default
+    pub fn debounce(mut self, milliseconds: u32) -> Combobox<Item> {
+        self.debounce = milliseconds;
+        self
+    }
+
+    /// 10 by default
+    pub fn max_suggestions(mut self, max_suggestions: usize) -> Combobox<Item> {
+        self.max_suggestions = max_suggestions;
+        self
+    }
+
+    /// the id of the input, for labels
+    pub fn input_id(&self) -> String {
+        format!("paro-combobox-{}", self.name)
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn selected(&self) -> Option<&Item> {
+        self.selected.as_ref()
+    }
+
+    /**
+     * Selects an item without the user, like the current value of a form
+     */
+    pub fn select(&mut self, label: &str, item: Item) {
+        self.text = label.to_owned();
+        self.selected = Some(item);
+        self.expanded = false;
+    }
+
+    pub fn clear(&mut self) {
+        self.text = "".to_owned();
+        self.selected = None;
+        self.suggestions = vec![];
+        self.expanded = false;
+    }
+
+    /**
+     * Shows the suggestions, in the given order
+     */
+    pub fn set_suggestions(&mut self, mut suggestions: Vec<Suggestion<Item>>) {
+        suggestions.truncate(self.max_suggestions);
+        self.suggestions = suggestions;
+    }
+}
+
+impl <Item: Clone + Send + Sync + 'static> ParoComponent for Combobox<Item> {
+    type Message = ComboboxMessage<Item>;
+
+    fn render<State: Send + Sync + 'static>(&self, scope: &ComponentScope<State, Self>) -> String {
+        let input_id = self.input_id();
+        let listbox_id = format!("{}-suggestions", input_id);
+        let expanded = self.expanded && !self.suggestions.is_empty();
+
+        let search = scope.event_with_modifiers(&format!("debounce={}", self.debounce),
+            |combobox: &mut Combobox<Item>, text: String, emitter| {
+                let had_selection = combobox.selected.take().is_some();
+                combobox.text = text.clone();
+                combobox.expanded = true;
+                if had_selection {
+                    emitter.emit(ComboboxMessage::Cleared);
+                }
+                emitter.emit(ComboboxMessage::Search(text));
+            });
+        // Escape closes the suggestions first and is left to others afterwards
+        let escape = scope.event_with_modifiers(if expanded { "key=Escape,prevent" } else { "key=Escape" },
+            |combobox: &mut Combobox<Item>, _: Option<String>, _| combobox.expanded = false);
+        // paro.js keeps the focus on the input when a suggestion is clicked, so this is
+        // only called if the user went somewhere else
+        let blur = scope.event(|combobox: &mut Combobox<Item>, _: Option<String>, _| combobox.expanded = false);
+
+        let mut html = format!(
+            r#"<div class="paro-combobox"><input id="{}" class="paro-combobox-input" type="text" role="combobox" autocomplete="off" aria-autocomplete="list" aria-expanded="{}" aria-controls="{}" data-paro-listbox="{}" aria-label="{}" placeholder="{}" value="{}" {}="{}" {}="{}" {}="{}">"#,
+            input_id, expanded, listbox_id, listbox_id, escape_html(&self.label), escape_html(&self.placeholder), escape_html(&self.text),
+            event_attribute("input"), escape_html(&search), event_attribute("keydown"), escape_html(&escape),
+            event_attribute("blur"), escape_html(&blur)
+        );
+        if expanded {
+            html.push_str(&format!(
+                r#"<ul id="{}" class="paro-combobox-suggestions" role="listbox" aria-label="{}">"#,
+                listbox_id, escape_html(&self.label)
+            ));
+            for (index, suggestion) in self.suggestions.iter().enumerate() {
+                let chosen = suggestion.clone();
+                let select = scope.event(move |combobox: &mut Combobox<Item>, _: Option<String>, emitter| {
+                    combobox.select(&chosen.label, chosen.item.clone());
+                    emitter.emit(ComboboxMessage::Selected(chosen.item.clone()));
+                });
+                html.push_str(&format!(
+                    r#"<li id="{}-{}" class="paro-combobox-suggestion" role="option" aria-selected="false" {}="{}">{}</li>"#,
+                    listbox_id, index, event_attribute("click"), escape_html(&select), escape_html(&suggestion.label)
+                ));
+            }
+            html.push_str("</ul>");
+        }
+        html.push_str("</div>");
+        html
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use super::*;
+    use crate::{render_component, EventMode, ParoApp};
+    use crate::tests::{delegated_callbacks, in_event_mode, wait_for_callback};
+
+    struct Form {
+        manager: Combobox<u8>,
+        messages: Vec<ComboboxMessage<u8>>,
+    }
+
+    fn render_form(paro_app: &mut Arc<RwLock<ParoApp<Form>>>) -> String {
+        let manager = paro_app.read().unwrap().state.manager.clone();
+        render_component(paro_app, &manager, |form: &mut Form| &mut form.manager,
+            |form: &mut Form, message: ComboboxMessage<u8>| {
+                if let ComboboxMessage::Search(text) = &message {
+                    let suggestions = [("Anna", 1), ("Bert", 2), ("Hanna", 3)].iter()
+                        .filter(|(label, _)| label.to_lowercase().contains(text.as_str()))
+                        .map(|(label, item)| Suggestion::new(label, *item))
+                        .collect();
+                    form.manager.set_suggestions(suggestions);
+                }
+                form.messages.push(message);
+            })
+    }
+
+    fn new_form(manager: Combobox<u8>) -> Arc<RwLock<ParoApp<Form>>> {
+        Arc::new(RwLock::new(ParoApp::new(Form { manager, messages: vec![] })))
+    }
+
+    fn call(paro_app: &Arc<RwLock<ParoApp<Form>>>, id: &str, value: &str) {
+        wait_for_callback(paro_app, id);
+        paro_app.write().unwrap().call(format!("{}__PARO__{}", id, value)).unwrap();
+    }
+
+    #[test]
+    fn selecting_a_suggestion_shows_its_label_and_closes_the_suggestions() {
+        let mut paro_app = new_form(Combobox::new("manager", "Manager").max_suggestions(1));
+
+        in_event_mode(EventMode::Delegated, || {
+            let search = delegated_callbacks(&render_form(&mut paro_app), "input").pop().unwrap();
+            call(&paro_app, &search, "n");
+            assert_eq!(paro_app.read().unwrap().state.messages, vec![ComboboxMessage::Search("n".to_owned())]);
+
+            let html = render_form(&mut paro_app);
+            assert!(html.contains(r#"aria-expanded="true""#));
+            // Anna and Hanna match, but only one suggestion is shown
+            let suggestions = delegated_callbacks(&html, "click");
+            assert_eq!(suggestions.len(), 1);
+            call(&paro_app, &suggestions[0], "");
+
+            let app = paro_app.read().unwrap();
+            assert_eq!(app.state.messages.last(), Some(&ComboboxMessage::Selected(1)));
+            assert_eq!(app.state.manager.text(), "Anna");
+            assert_eq!(app.state.manager.selected(), Some(&1));
+            drop(app);
+            let html = render_form(&mut paro_app);
+            assert!(html.contains(r#"value="Anna""#) && html.contains(r#"aria-expanded="false""#));
+            assert!(!html.contains("paro-combobox-suggestion\""));
+        });
+    }
+
+    #[test]
+    fn typing_after_a_selection_clears_it() {
+        let mut combobox = Combobox::new("manager", "Manager");
+        combobox.select("Anna", 1);
+        let mut paro_app = new_form(combobox);
+
+        in_event_mode(EventMode::Delegated, || {
+            let search = delegated_callbacks(&render_form(&mut paro_app), "input").pop().unwrap();
+            call(&paro_app, &search, "ann");
+            {
+                let app = paro_app.read().unwrap();
+                assert_eq!(app.state.messages, vec![ComboboxMessage::Cleared, ComboboxMessage::Search("ann".to_owned())]);
+                assert_eq!(app.state.manager.text(), "ann");
+                assert_eq!(app.state.manager.selected(), None);
+            }
+
+            // nothing is selected anymore, so there is nothing to clear
+            let search = delegated_callbacks(&render_form(&mut paro_app), "input").pop().unwrap();
+            call(&paro_app, &search, "anna");
+            assert_eq!(paro_app.read().unwrap().state.messages[2..], [ComboboxMessage::Search("anna".to_owned())]);
+        });
+    }
+
+    #[test]
+    fn escape_closes_the_suggestions() {
+        let mut paro_app = new_form(Combobox::new("manager", "Manager"));
+
+        in_event_mode(EventMode::Delegated, || {
+            let search = delegated_callbacks(&render_form(&mut paro_app), "input").pop().unwrap();
+            call(&paro_app, &search, "a");
+            let html = render_form(&mut paro_app);
+            assert!(html.contains("data-paro-keydown=\"") && html.contains(":key=Escape,prevent\""));
+            let escape = delegated_callbacks(&html, "keydown").pop().unwrap();
+            call(&paro_app, &escape, "");
+
+            let html = render_form(&mut paro_app);
+            assert!(html.contains(r#"aria-expanded="false""#));
+            // closed suggestions leave Escape to others
+            assert!(html.contains(":key=Escape\""));
+            // closing is no choice of the user
+            assert_eq!(paro_app.read().unwrap().state.messages, vec![ComboboxMessage::Search("a".to_owned())]);
+        });
+    }
+
+    #[test]
+    fn clear_forgets_text_selection_and_suggestions() {
+        let mut combobox = Combobox::new("manager", "Manager");
+        combobox.set_suggestions(vec![Suggestion::new("Anna", 1)]);
+        combobox.select("Anna", 1);
+        combobox.clear();
+        assert_eq!(combobox.text(), "");
+        assert_eq!(combobox.selected(), None);
+        assert!(combobox.suggestions.is_empty());
+    }
+}
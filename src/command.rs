@@ -0,0 +1,78 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::Serialize;
+
+
+/**
+ * A client side effect paro.js executes after applying the next render.
+ * Queue commands via `ParoContext::command` or return `ParoAction::Command`:
+ *
+ * ```ignore
+ * event!(paro_app, |state, _, ctx| {
+ *     state.save();
+ *     ctx.command(ParoCommand::scroll_into_view("[data-employee-id='42']"));
+ *     ctx.command(ParoCommand::focus("#search"));
+ * })
+ * ```
+ *
+ * paro.js looks up commands in `window.__PARO__.commands` by their name, so
+ * you can replace or extend them there.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "command", rename_all = "camelCase")]
+pub enum ParoCommand {
+    /// Focus the element matching the css selector
+    Focus { selector: String },
+    /// Scroll the element matching the css selector into view
+    ScrollIntoView { selector: String },
+    /// Set `document.title`
+    SetTitle { title: String },
+    /// Copy the text to the clipboard of the user
+    CopyToClipboard { text: String },
+    /// Add the css class to the element matching the selector and remove it
+    /// once its css animation ended, so it can be played again
+    Animate { selector: String, class: String },
+    /// Let the user download the base64 encoded data as a file with the given name
+    Download { name: String, mime: String, data: String },
+    /// Show the base64 encoded data in the `<img>` or `<iframe>` matching the
+    /// selector, via an object url. Rendering the element again removes it.
+    ShowBlob { selector: String, mime: String, data: String },
+}
+
+impl ParoCommand {
+    pub fn focus(selector: &str) -> ParoCommand {
+        ParoCommand::Focus { selector: selector.to_owned() }
+    }
+
+    pub fn scroll_into_view(selector: &str) -> ParoCommand {
+        ParoCommand::ScrollIntoView { selector: selector.to_owned() }
+    }
+
+    pub fn set_title(title: &str) -> ParoCommand {
+        ParoCommand::SetTitle { title: title.to_owned() }
+    }
+
+    pub fn copy_to_clipboard(text: &str) -> ParoCommand {
+        ParoCommand::CopyToClipboard { text: text.to_owned() }
+    }
+
+    pub fn animate(selector: &str, class: &str) -> ParoCommand {
+        ParoCommand::Animate { selector: selector.to_owned(), class: class.to_owned() }
+    }
+
+    /**
+     * Lets the user download the bytes as a file, like a csv or pdf you generated
+     */
+    pub fn download(name: &str, mime: &str, bytes: &[u8]) -> ParoCommand {
+        ParoCommand::Download { name: name.to_owned(), mime: mime.to_owned(), data: BASE64.encode(bytes) }
+    }
+
+    /**
+     * Shows the bytes in the `<img>` or `<iframe>` matching the selector, for
+     * example a pdf preview. Put the element into a region that is not rendered
+     * again or send the command again after rendering it.
+     */
+    pub fn show_blob(selector: &str, mime: &str, bytes: &[u8]) -> ParoCommand {
+        ParoCommand::ShowBlob { selector: selector.to_owned(), mime: mime.to_owned(), data: BASE64.encode(bytes) }
+    }
+}
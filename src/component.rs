@@ -0,0 +1,227 @@
+use std::sync::{Arc, RwLock};
+
+use uuid::Uuid;
+
+use crate::{FromParoValue, Hotkey, IntoParoAction, ParoApp};
+use crate::__private::{emit_event_call, register};
+
+
+/**
+ * A reusable piece of gui that owns its local state, renders itself and
+ * handles its own events. Its callbacks receive the component instead of your
+ * whole application state and can send typed messages to the parent, so a
+ * component does not need to know anything about the application it is used in.
+ *
+ * ```ignore
+ * pub struct Pagination {
+ *     pub current_page: usize,
+ *     pub page_count: usize,
+ * }
+ *
+ * pub enum PaginationMessage {
+ *     PageChanged(usize),
+ * }
+ *
+ * impl ParoComponent for Pagination {
+ *     type Message = PaginationMessage;
+ *
+ *     fn render<State: Send + Sync + 'static>(&self, scope: &ComponentScope<State, Self>) -> String {
+ *         let next = scope.event(|pagination: &mut Pagination, _: Option<String>, emitter| {
+ *             pagination.current_page += 1;
+ *             emitter.emit(PaginationMessage::PageChanged(pagination.current_page));
+ *         });
+ *         format!(r#"<button onclick="{}">next page</button>"#, next)
+ *     }
+ * }
+ *
+ * // in the parent
+ * let html = render_component(
+ *     paro_app,
+ *     &state.pagination,
+ *     |state: &mut ApplicationState| &mut state.pagination,
+ *     |state: &mut ApplicationState, message: PaginationMessage| match message {
+ *         PaginationMessage::PageChanged(page) => state.load_page(page),
+ *     },
+ * );
+ * ```
+ */
+pub trait ParoComponent: Sized + Send + Sync + 'static {
+    /// Messages the component sends to its parent
+    type Message: Send + 'static;
+
+    /**
+     * Render the component to html. Use `scope.event` instead of event! to create
+     * callbacks that receive the component and `scope.render_child` to render
+     * components nested in this one.
+     */
+    fn render<State: Send + Sync + 'static>(&self, scope: &ComponentScope<State, Self>) -> String;
+}
+
+
+/**
+ * Passed to component callbacks to send messages to the parent component
+ * or application. Messages are delivered once the callback returns.
+ */
+pub struct Emitter<Message> {
+    messages: Vec<Message>,
+}
+
+impl <Message> Emitter<Message> {
+    fn new() -> Emitter<Message> {
+        Emitter {
+            messages: vec![],
+        }
+    }
+
+    pub fn emit(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+}
+
+
+type Lens<State, Component> = Arc<dyn for<'a> Fn(&'a mut State) -> &'a mut Component + Send + Sync + 'static>;
+type Deliver<State, Message> = Arc<dyn Fn(&mut State, Vec<Message>) + Send + Sync + 'static>;
+
+// makes sure closures returning references get the right signature
+fn lens<State, Component, L>(lens: L) -> L
+where
+    L: for<'a> Fn(&'a mut State) -> &'a mut Component,
+{
+    lens
+}
+
+
+/**
+ * Knows where a component lives inside your application state and where its
+ * messages go. Passed to `ParoComponent::render`.
+ */
+pub struct ComponentScope<State, Component: ParoComponent> {
+    paro_app: Arc<RwLock<ParoApp<State>>>,
+    lens: Lens<State, Component>,
+    deliver: Deliver<State, Component::Message>,
+}
+
+impl <State, Component> ComponentScope<State, Component>
+where
+    State: Send + Sync + 'static,
+    Component: ParoComponent,
+{
+    /**
+     * Creates a callback that receives this component, the value of the element
+     * (parsed like with event!) and an `Emitter` for messages to the parent.
+     * Like with event!, the callback can return a `ParoAction`.
+     * Returns the javascript to put into your html, just like event! does.
+     */
+    pub fn event<Value, Action, Callback>(&self, callback: Callback) -> String
+    where
+        Value: FromParoValue,
+        Action: IntoParoAction,
+        Callback: FnMut(&mut Component, Value, &mut Emitter<Component::Message>) -> Action + Send + Sync + 'static,
+    {
+        self.event_with_modifiers("", callback)
+    }
+
+    /**
+     * Like `event`, with modifiers encoded the way event! does, like
+     * `key=Escape,prevent` or `debounce=200`.
+     */
+    pub fn event_with_modifiers<Value, Action, Callback>(&self, modifiers: &str, mut callback: Callback) -> String
+    where
+        Value: FromParoValue,
+        Action: IntoParoAction,
+        Callback: FnMut(&mut Component, Value, &mut Emitter<Component::Message>) -> Action + Send + Sync + 'static,
+    {
+        let lens = self.lens.clone();
+        let deliver = self.deliver.clone();
+        let callback_id = Uuid::new_v4().to_string();
+        register(&self.paro_app, callback_id.clone(), false, move |state: &mut State, value: Value| {
+            let mut emitter = Emitter::new();
+            let action = callback(lens(state), value, &mut emitter);
+            if !emitter.messages.is_empty() {
+                deliver(state, emitter.messages);
+            }
+            action
+        });
+        emit_event_call(&callback_id, modifiers)
+    }
+
+    /**
+     * Declares a keyboard shortcut whose callback receives this component and
+     * the context of the application, see `hotkey`
+     */
+    pub fn hotkey(&self, hotkey: Hotkey<Component, State>) -> String {
+        let lens = self.lens.clone();
+        crate::hotkey(&self.paro_app, hotkey.lens(move |state: &mut State| lens(state)))
+    }
+
+    /**
+     * Renders a component nested in this one. `child_lens` selects the child inside
+     * this component and `on_message` handles the messages the child emits.
+     */
+    pub fn render_child<Child, ChildLens, OnMessage>(&self, child: &Child, child_lens: ChildLens, on_message: OnMessage) -> String
+    where
+        Child: ParoComponent,
+        ChildLens: for<'a> Fn(&'a mut Component) -> &'a mut Child + Send + Sync + 'static,
+        OnMessage: Fn(&mut Component, Child::Message, &mut Emitter<Component::Message>) + Send + Sync + 'static,
+    {
+        let parent_lens = self.lens.clone();
+        let parent_deliver = self.deliver.clone();
+        let deliver_lens = self.lens.clone();
+        let scope = ComponentScope::<State, Child> {
+            paro_app: self.paro_app.clone(),
+            lens: Arc::new(lens(move |state: &mut State| child_lens(parent_lens(state)))),
+            deliver: Arc::new(move |state: &mut State, messages: Vec<Child::Message>| {
+                let mut emitter = Emitter::new();
+                let parent = deliver_lens(state);
+                for message in messages {
+                    on_message(parent, message, &mut emitter);
+                }
+                if !emitter.messages.is_empty() {
+                    parent_deliver(state, emitter.messages);
+                }
+            }),
+        };
+        child.render(&scope)
+    }
+}
+
+impl <State, Component: ParoComponent> Clone for ComponentScope<State, Component> {
+    fn clone(&self) -> Self {
+        ComponentScope {
+            paro_app: self.paro_app.clone(),
+            lens: self.lens.clone(),
+            deliver: self.deliver.clone(),
+        }
+    }
+}
+
+
+/**
+ * Renders a component that lives in your application state. `component_lens`
+ * selects the component inside your state and `on_message` handles the
+ * messages the component emits.
+ * Do not hold a write lock on the `ParoApp` while rendering.
+ */
+pub fn render_component<State, Component, ComponentLens, OnMessage>(
+    paro_app: &Arc<RwLock<ParoApp<State>>>,
+    component: &Component,
+    component_lens: ComponentLens,
+    on_message: OnMessage,
+) -> String
+where
+    State: Send + Sync + 'static,
+    Component: ParoComponent,
+    ComponentLens: for<'a> Fn(&'a mut State) -> &'a mut Component + Send + Sync + 'static,
+    OnMessage: Fn(&mut State, Component::Message) + Send + Sync + 'static,
+{
+    let scope = ComponentScope::<State, Component> {
+        paro_app: paro_app.clone(),
+        lens: Arc::new(component_lens),
+        deliver: Arc::new(move |state: &mut State, messages: Vec<Component::Message>| {
+            for message in messages {
+                on_message(state, message);
+            }
+        }),
+    };
+    component.render(&scope)
+}
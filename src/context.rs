@@ -0,0 +1,217 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::{IntoParoAction, Level, ParoAction, ParoCanvas, ParoClient, ParoCommand, ParoDialog, ParoImages, ParoStorage, StorageArea, Toast, ToastHandle};
+use crate::dialog::{DialogChange, DialogHandle, OpenDialog};
+use crate::toast::ToastChange;
+
+
+/**
+ * Lets your callbacks tell pâro what should happen after they ran. Add it as
+ * third argument to your callback to get access to it, typed by the state of
+ * your application:
+ *
+ * ```ignore
+ * event!(paro_app, |state: &mut ApplicationState, value: String, ctx: &mut ParoContext<ApplicationState>| {
+ *     state.search_term = value;
+ *     ctx.mark_dirty("results");
+ * })
+ * ```
+ */
+pub struct ParoContext<State> {
+    dirty_regions: BTreeSet<String>,
+    skip_render: bool,
+    toasts: Vec<ToastChange<State>>,
+    commands: Vec<ParoCommand>,
+    client: ParoClient,
+    local_storage: ParoStorage,
+    session_storage: ParoStorage,
+    images: ParoImages,
+    dialogs: Vec<DialogChange<State>>,
+}
+
+impl <State> fmt::Debug for ParoContext<State> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParoContext")
+            .field("dirty_regions", &self.dirty_regions)
+            .field("skip_render", &self.skip_render)
+            .field("toasts", &self.toasts)
+            .field("commands", &self.commands)
+            .field("dialogs", &self.dialogs)
+            .finish_non_exhaustive()
+    }
+}
+
+impl <State: 'static> Default for ParoContext<State> {
+    fn default() -> Self {
+        ParoContext::new()
+    }
+}
+
+impl <State: 'static> ParoContext<State> {
+    pub fn new() -> ParoContext<State> {
+        let client = ParoClient::default();
+        ParoContext {
+            dirty_regions: BTreeSet::new(),
+            skip_render: false,
+            toasts: vec![],
+            commands: vec![],
+            local_storage: ParoStorage::new(StorageArea::Local, client.clone()),
+            session_storage: ParoStorage::new(StorageArea::Session, client.clone()),
+            images: ParoImages::new(client.clone()),
+            dialogs: vec![],
+            client,
+        }
+    }
+
+    /**
+     * Only re-render the region with the given name instead of the whole page.
+     * Can be called multiple times to re-render multiple regions. Regions are
+     * elements with a `data-paro-region="<name>"` attribute whose content was
+     * rendered via `render_region`.
+     */
+    pub fn mark_dirty(&mut self, region: &str) {
+        self.dirty_regions.insert(region.to_owned());
+    }
+
+    /**
+     * Does what the action asks for, just like returning it from the callback would
+     */
+    pub fn apply(&mut self, action: ParoAction) {
+        match action {
+            ParoAction::Render => {},
+            ParoAction::NoRender => self.skip_render = true,
+            ParoAction::RenderRegion(region) => self.mark_dirty(&region),
+            ParoAction::Toast(message) => {
+                self.toast(Level::Info, &message);
+            },
+            ParoAction::Focus(selector) => self.command(ParoCommand::Focus { selector }),
+            ParoAction::Command(command) => self.command(command),
+        }
+    }
+
+    /**
+     * Queue a command paro.js executes after the next render, like focusing an
+     * element or setting the title. Commands are executed in the order queued.
+     */
+    pub fn command(&mut self, command: ParoCommand) {
+        self.commands.push(command);
+    }
+
+    /**
+     * Lets you ask paro.js about things only the client knows, like the size of
+     * an element. See `ParoClient`.
+     */
+    pub fn client(&self) -> ParoClient {
+        self.client.clone()
+    }
+
+    /**
+     * Values in the `localStorage` of the webview, see `ParoStorage`
+     */
+    pub fn storage(&self) -> ParoStorage {
+        self.local_storage.clone()
+    }
+
+    /**
+     * Values in the `sessionStorage` of the webview, see `ParoStorage`
+     */
+    pub fn session_storage(&self) -> ParoStorage {
+        self.session_storage.clone()
+    }
+
+    /**
+     * Images generated in rust, see `ParoImages`
+     */
+    pub fn images(&self) -> ParoImages {
+        self.images.clone()
+    }
+
+    /**
+     * Draws on the `<canvas data-paro-canvas="<name>">`, see `ParoCanvas`
+     */
+    pub fn canvas(&self, name: &str) -> ParoCanvas {
+        ParoCanvas::new(name, self.client.clone())
+    }
+
+    /**
+     * Shows a short message above the page, see `Toast`
+     */
+    pub fn toast(&mut self, level: Level, message: &str) -> ToastHandle {
+        self.show_toast(Toast::new(level, message))
+    }
+
+    /**
+     * Shows the toast above the page, once there is room for it
+     */
+    pub fn show_toast(&mut self, toast: Toast<State>) -> ToastHandle {
+        let handle = toast.handle();
+        self.toasts.push(ToastChange::Show(toast));
+        handle
+    }
+
+    /**
+     * Removes the toast, even if it is still waiting to be shown
+     */
+    pub fn dismiss_toast(&mut self, toast: &ToastHandle) {
+        self.toasts.push(ToastChange::Dismiss(toast.id().to_owned()));
+    }
+
+    /**
+     * Opens a dialog above the page and above the dialogs that are already
+     * open. Once it is closed, `on_close` is called with its result. See
+     * `ParoDialog`.
+     */
+    pub fn open_dialog<Dialog, Action, OnClose>(&mut self, dialog: Dialog, on_close: OnClose) -> DialogHandle<Dialog::Result>
+    where
+        Dialog: ParoDialog<State>,
+        Action: IntoParoAction,
+        OnClose: FnOnce(&mut State, Dialog::Result, &mut ParoContext<State>) -> Action + Send + Sync + 'static,
+    {
+        let (open, handle) = OpenDialog::new(dialog, on_close);
+        self.dialogs.push(DialogChange::Open(Box::new(open)));
+        handle
+    }
+
+    /**
+     * Closes the dialog and passes the result to the callback that opened it
+     */
+    pub fn close_dialog<Result: Send + Sync + 'static>(&mut self, dialog: &DialogHandle<Result>, result: Result) {
+        self.dialogs.push(DialogChange::Close(dialog.id().to_owned(), Box::new(result)));
+    }
+
+    pub(crate) fn change_dialogs(&mut self, change: DialogChange<State>) {
+        self.dialogs.push(change);
+    }
+
+    pub(crate) fn take_dialog_changes(&mut self) -> Vec<DialogChange<State>> {
+        std::mem::take(&mut self.dialogs)
+    }
+
+    /**
+     * Takes the regions marked dirty since the last render. Empty if the whole
+     * page should be rendered.
+     */
+    pub(crate) fn take_dirty_regions(&mut self) -> BTreeSet<String> {
+        std::mem::take(&mut self.dirty_regions)
+    }
+
+    pub(crate) fn skips_render(&self) -> bool {
+        self.skip_render
+    }
+
+    /**
+     * Takes whether a callback asked not to render anything since the last render
+     */
+    pub(crate) fn take_skip_render(&mut self) -> bool {
+        std::mem::take(&mut self.skip_render)
+    }
+
+    pub(crate) fn take_toasts(&mut self) -> Vec<ToastChange<State>> {
+        std::mem::take(&mut self.toasts)
+    }
+
+    pub(crate) fn take_commands(&mut self) -> Vec<ParoCommand> {
+        std::mem::take(&mut self.commands)
+    }
+}
@@ -0,0 +1,441 @@
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, RwLock};
+
+use uuid::Uuid;
+
+use crate::{event_attribute, IntoParoAction, ParoAction, ParoApp, ParoContext, ParoError};
+use crate::__private::{emit_event_call, register_with_context};
+use crate::render::escape_html;
+
+
+/**
+ * A modal dialog, shown above the page until it is closed. Open it from a
+ * callback via `ParoContext::open_dialog` and close it from one of the
+ * callbacks it renders via `ParoContext::close_dialog`, which passes the
+ * result to the callback that opened it:
+ *
+ * ```ignore
+ * struct ConfirmDelete { name: String }
+ *
+ * impl ParoDialog<ApplicationState> for ConfirmDelete {
+ *     type Result = bool;
+ *
+ *     fn title(&self) -> String {
+ *         format!("Delete {}?", self.name)
+ *     }
+ *
+ *     fn render(&self, paro_app: &mut Arc<RwLock<ParoApp<ApplicationState>>>, dialog: &DialogHandle<bool>) -> String {
+ *         let dialog = dialog.clone();
+ *         html! {
+ *             button onclick=(event!(paro_app, move |_: &mut ApplicationState, _: Option<String>, ctx: &mut ParoContext<ApplicationState>| {
+ *                 ctx.close_dialog(&dialog, true);
+ *             })) { "Delete" }
+ *         }.into_string()
+ *     }
+ *
+ *     fn dismissed(&self) -> Option<bool> {
+ *         Some(false)
+ *     }
+ * }
+ * ```
+ *
+ * paro.js keeps the focus inside the dialog on top while it is open and gives
+ * it back to where it was once it closes. Style dialogs via the
+ * `paro-dialog-backdrop`, `paro-dialog` and `paro-dialog-title` classes.
+ */
+pub trait ParoDialog<State>: Send + Sync + 'static {
+    /**
+     * What closing the dialog passes to the callback that opened it
+     */
+    type Result: Send + Sync + 'static;
+
+    /**
+     * Shown as heading of the dialog and read by screen readers
+     */
+    fn title(&self) -> String;
+
+    /**
+     * The content of the dialog below its title. Rendered again with every
+     * render, just like the page.
+     */
+    fn render(&self, paro_app: &mut Arc<RwLock<ParoApp<State>>>, dialog: &DialogHandle<Self::Result>) -> String;
+
+    /**
+     * The result of closing the dialog via Escape or a click on the backdrop.
+     * `None`, the default, keeps the dialog open.
+     */
+    fn dismissed(&self) -> Option<Self::Result> {
+        None
+    }
+}
+
+
+/**
+ * Identifies an open dialog, to close it with a result of the right type
+ */
+pub struct DialogHandle<Result> {
+    id: String,
+    result: PhantomData<fn() -> Result>,
+}
+
+impl <Result> DialogHandle<Result> {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl <Result> Clone for DialogHandle<Result> {
+    fn clone(&self) -> Self {
+        DialogHandle { id: self.id.clone(), result: PhantomData }
+    }
+}
+
+impl <Result> fmt::Debug for DialogHandle<Result> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DialogHandle").field(&self.id).finish()
+    }
+}
+
+
+// the result of a dialog, not knowing its type
+type AnyResult = Box<dyn Any + Send + Sync>;
+
+type CloseCallback<State> = Box<dyn FnOnce(&mut State, AnyResult, &mut ParoContext<State>) + Send + Sync + 'static>;
+
+/**
+ * A dialog callbacks opened or closed, applied to the dialogs of the
+ * `ParoApp` before the next render
+ */
+pub(crate) enum DialogChange<State> {
+    Open(Box<OpenDialog<State>>),
+    Close(String, AnyResult),
+}
+
+impl <State> fmt::Debug for DialogChange<State> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DialogChange::Open(_) => f.write_str("Open"),
+            DialogChange::Close(id, _) => f.debug_tuple("Close").field(id).finish(),
+        }
+    }
+}
+
+// ParoDialog, not knowing the type of its result
+pub(crate) trait AnyDialog<State>: Send + Sync {
+    fn title(&self) -> String;
+    fn render(&self, paro_app: &mut Arc<RwLock<ParoApp<State>>>, id: &str) -> String;
+    fn dismissed(&self) -> Option<AnyResult>;
+}
+
+impl <State, Dialog: ParoDialog<State>> AnyDialog<State> for Dialog {
+    fn title(&self) -> String {
+        ParoDialog::title(self)
+    }
+
+    fn render(&self, paro_app: &mut Arc<RwLock<ParoApp<State>>>, id: &str) -> String {
+        ParoDialog::render(self, paro_app, &DialogHandle { id: id.to_owned(), result: PhantomData })
+    }
+
+    fn dismissed(&self) -> Option<AnyResult> {
+        ParoDialog::dismissed(self).map(|result| Box::new(result) as AnyResult)
+    }
+}
+
+// an open dialog as it is rendered, by id
+pub(crate) type RenderedDialog<State> = (String, Arc<dyn AnyDialog<State>>);
+
+pub(crate) struct OpenDialog<State> {
+    id: String,
+    dialog: Arc<dyn AnyDialog<State>>,
+    on_close: CloseCallback<State>,
+}
+
+impl <State: 'static> OpenDialog<State> {
+    pub(crate) fn new<Dialog, Action, OnClose>(dialog: Dialog, on_close: OnClose) -> (OpenDialog<State>, DialogHandle<Dialog::Result>)
+    where
+        Dialog: ParoDialog<State>,
+        Action: IntoParoAction,
+        OnClose: FnOnce(&mut State, Dialog::Result, &mut ParoContext<State>) -> Action + Send + Sync + 'static,
+    {
+        let id = Uuid::new_v4().to_string();
+        let open = OpenDialog {
+            id: id.clone(),
+            dialog: Arc::new(dialog),
+            on_close: Box::new(move |state, result, context| {
+                // close_dialog only accepts results of the type of the handle
+                if let Ok(result) = result.downcast::<Dialog::Result>() {
+                    on_close(state, *result, context).apply(context);
+                }
+            }),
+        };
+        (open, DialogHandle { id, result: PhantomData })
+    }
+}
+
+
+impl <State: 'static> ParoApp<State> {
+    /**
+     * Opens and closes the dialogs callbacks asked for, calling the callbacks
+     * of the dialogs that were closed. Those may open or close dialogs again.
+     */
+    pub(crate) fn update_dialogs(&mut self) {
+        loop {
+            let changes = self.context.take_dialog_changes();
+            if changes.is_empty() {
+                return;
+            }
+            for change in changes {
+                match change {
+                    DialogChange::Open(dialog) => {
+                        self.dialogs.push(*dialog);
+                        self.dialogs_changed = true;
+                    },
+                    DialogChange::Close(id, result) => match self.dialogs.iter().position(|dialog| dialog.id == id) {
+                        Some(index) => {
+                            let dialog = self.dialogs.remove(index);
+                            self.dialogs_changed = true;
+                            (dialog.on_close)(&mut self.state, result, &mut self.context);
+                        },
+                        None => self.context.client().report(ParoError::DialogNotOpen(id)),
+                    },
+                }
+            }
+        }
+    }
+
+    /**
+     * The user pressed Escape or clicked the backdrop of a dialog
+     */
+    pub(crate) fn dismiss_dialog(&mut self, id: &str) {
+        let dismissed = self.dialogs.iter()
+            .find(|dialog| dialog.id == id)
+            .and_then(|dialog| dialog.dialog.dismissed());
+        match dismissed {
+            Some(result) => self.context.change_dialogs(DialogChange::Close(id.to_owned(), result)),
+            None => self.context.apply(ParoAction::NoRender),
+        }
+    }
+
+    /**
+     * The dialogs to send with the next render, `None` if paro.js can keep
+     * the ones it has
+     */
+    pub(crate) fn take_dialogs_to_render(&mut self, skip_render: bool) -> Option<Vec<RenderedDialog<State>>> {
+        let changed = std::mem::take(&mut self.dialogs_changed);
+        if !changed && (skip_render || self.dialogs.is_empty()) {
+            return None;
+        }
+        Some(self.dialogs.iter().map(|dialog| (dialog.id.clone(), dialog.dialog.clone())).collect())
+    }
+}
+
+
+/**
+ * Renders the open dialogs, the last one on top. Only the one on top can be
+ * used, the others are inert.
+ */
+pub(crate) fn render_dialogs<State>(paro_app: &mut Arc<RwLock<ParoApp<State>>>, dialogs: Vec<RenderedDialog<State>>) -> String {
+    let count = dialogs.len();
+    dialogs.into_iter().enumerate().map(|(index, (id, dialog))| {
+        let on_top = index + 1 == count;
+        format!(
+            concat!(
+                "<div class=\"paro-dialog-backdrop\" data-paro-dialog=\"{id}\"{dismissible}{inert}>",
+                "<div class=\"paro-dialog\" role=\"dialog\" aria-modal=\"true\" aria-labelledby=\"paro-dialog-{id}-title\" tabindex=\"-1\">",
+                "<h2 class=\"paro-dialog-title\" id=\"paro-dialog-{id}-title\">{title}</h2>{content}</div></div>",
+            ),
+            id = id,
+            dismissible = if dialog.dismissed().is_some() { " data-paro-dismissible" } else { "" },
+            inert = if on_top { "" } else { " inert" },
+            title = escape_html(&dialog.title()),
+            content = dialog.render(paro_app, &id),
+        )
+    }).collect()
+}
+
+
+/**
+ * Asks the user to confirm something, see `ParoAsyncContext::confirm`.
+ * Closes with true if they did.
+ */
+pub struct ConfirmDialog {
+    question: String,
+    confirm_label: String,
+    cancel_label: String,
+}
+
+impl ConfirmDialog {
+    pub fn new(question: &str) -> ConfirmDialog {
+        ConfirmDialog {
+            question: question.to_owned(),
+            confirm_label: "OK".to_owned(),
+            cancel_label: "Cancel".to_owned(),
+        }
+    }
+
+    /**
+     * The text of the buttons, "OK" and "Cancel" by default
+     */
+    pub fn labels(mut self, confirm: &str, cancel: &str) -> ConfirmDialog {
+        self.confirm_label = confirm.to_owned();
+        self.cancel_label = cancel.to_owned();
+        self
+    }
+}
+
+impl <State: Send + Sync + 'static> ParoDialog<State> for ConfirmDialog {
+    type Result = bool;
+
+    fn title(&self) -> String {
+        self.question.clone()
+    }
+
+    fn render(&self, paro_app: &mut Arc<RwLock<ParoApp<State>>>, dialog: &DialogHandle<bool>) -> String {
+        format!(
+            r#"<div class="paro-dialog-buttons"><button type="button" {}="{}">{}</button><button type="button" autofocus {}="{}">{}</button></div>"#,
+            event_attribute("click"), escape_html(&close_with(paro_app, dialog, false)), escape_html(&self.cancel_label),
+            event_attribute("click"), escape_html(&close_with(paro_app, dialog, true)), escape_html(&self.confirm_label)
+        )
+    }
+
+    fn dismissed(&self) -> Option<bool> {
+        Some(false)
+    }
+}
+
+
+/**
+ * Asks the user for a text, see `ParoAsyncContext::prompt`. Closes with
+ * None if they canceled.
+ */
+pub struct PromptDialog {
+    question: String,
+    // what the user typed so far
+    value: Arc<Mutex<String>>,
+    confirm_label: String,
+    cancel_label: String,
+}
+
+impl PromptDialog {
+    pub fn new(question: &str, value: &str) -> PromptDialog {
+        PromptDialog {
+            question: question.to_owned(),
+            value: Arc::new(Mutex::new(value.to_owned())),
+            confirm_label: "OK".to_owned(),
+            cancel_label: "Cancel".to_owned(),
+        }
+    }
+
+    /**
+     * The text of the buttons, "OK" and "Cancel" by default
+     */
+    pub fn labels(mut self, confirm: &str, cancel: &str) -> PromptDialog {
+        self.confirm_label = confirm.to_owned();
+        self.cancel_label = cancel.to_owned();
+        self
+    }
+}
+
+impl <State: Send + Sync + 'static> ParoDialog<State> for PromptDialog {
+    type Result = Option<String>;
+
+    fn title(&self) -> String {
+        self.question.clone()
+    }
+
+    fn render(&self, paro_app: &mut Arc<RwLock<ParoApp<State>>>, dialog: &DialogHandle<Option<String>>) -> String {
+        let input_id = Uuid::new_v4().to_string();
+        let value = self.value.clone();
+        register_with_context(paro_app, input_id.clone(), false, move |_state: &mut State, text: Option<String>, context: &mut ParoContext<State>| {
+            *value.lock().unwrap() = text.unwrap_or_default();
+            context.apply(ParoAction::NoRender);
+        });
+        let submit_id = Uuid::new_v4().to_string();
+        let value = self.value.clone();
+        let submitted = dialog.clone();
+        register_with_context(paro_app, submit_id.clone(), true, move |_state: &mut State, _: Option<String>, context: &mut ParoContext<State>| {
+            let value = value.lock().unwrap().clone();
+            context.close_dialog(&submitted, Some(value));
+        });
+        format!(
+            concat!(
+                r#"<form class="paro-dialog-prompt" {}="{}">"#,
+                r#"<input type="text" autofocus aria-label="{}" value="{}" {}="{}">"#,
+                r#"<div class="paro-dialog-buttons"><button type="button" {}="{}">{}</button><button type="submit">{}</button></div>"#,
+                "</form>",
+            ),
+            event_attribute("submit"), escape_html(&emit_event_call(&submit_id, "prevent,once")),
+            escape_html(&self.question), escape_html(&self.value.lock().unwrap()),
+            event_attribute("input"), escape_html(&emit_event_call(&input_id, "")),
+            event_attribute("click"), escape_html(&close_with(paro_app, dialog, None)), escape_html(&self.cancel_label),
+            escape_html(&self.confirm_label)
+        )
+    }
+
+    fn dismissed(&self) -> Option<Option<String>> {
+        Some(None)
+    }
+}
+
+// a click handler that closes the dialog with the result
+fn close_with<State, Result>(paro_app: &Arc<RwLock<ParoApp<State>>>, dialog: &DialogHandle<Result>, result: Result) -> String
+where
+    State: Send + Sync + 'static,
+    Result: Clone + Send + Sync + 'static,
+{
+    let id = Uuid::new_v4().to_string();
+    let dialog = dialog.clone();
+    register_with_context(paro_app, id.clone(), true, move |_state: &mut State, _: Option<String>, context: &mut ParoContext<State>| {
+        context.close_dialog(&dialog, result.clone());
+    });
+    emit_event_call(&id, "once")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Question;
+
+    impl <State> ParoDialog<State> for Question {
+        type Result = bool;
+
+        fn title(&self) -> String {
+            "Question".to_owned()
+        }
+
+        fn render(&self, _paro_app: &mut Arc<RwLock<ParoApp<State>>>, _dialog: &DialogHandle<bool>) -> String {
+            "".to_owned()
+        }
+    }
+
+    #[test]
+    fn closing_a_dialog_passes_its_result_to_the_callback() {
+        let mut app = ParoApp::new(Vec::<bool>::new());
+        let dialog = app.context().open_dialog(Question, |answers: &mut Vec<bool>, answer: bool, _: &mut ParoContext<Vec<bool>>| {
+            answers.push(answer)
+        });
+        app.update_dialogs();
+        assert_eq!(app.dialogs.len(), 1);
+
+        app.context().close_dialog(&dialog, true);
+        app.update_dialogs();
+        assert!(app.dialogs.is_empty());
+        assert_eq!(app.state, vec![true]);
+    }
+
+    #[test]
+    fn reports_closing_a_dialog_that_is_not_open() {
+        let mut app = crate::tests::collecting_errors();
+        let dialog = app.context().open_dialog(Question, |_: &mut Vec<ParoError>, _: bool, _: &mut ParoContext<Vec<ParoError>>| {});
+        app.update_dialogs();
+        app.context().close_dialog(&dialog, true);
+        app.context().close_dialog(&dialog, false);
+        app.update_dialogs();
+        app.handle_reported_errors();
+        assert_eq!(app.state, vec![ParoError::DialogNotOpen(dialog.id().to_owned())]);
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{FromParoValue, IntoParoAction, ParoApp, ParoContext};
+use crate::__private::register_with_context;
+
+
+/**
+ * Where the dragged element was dropped, relative to the drop zone
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DropPosition {
+    Before,
+    After,
+    Inside,
+}
+
+impl DropPosition {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DropPosition::Before => "before",
+            DropPosition::After => "after",
+            DropPosition::Inside => "inside",
+        }
+    }
+}
+
+
+/**
+ * Something was dropped on a drop zone, see `on_drop`. `payload` is the
+ * `data-paro-drag` attribute of the dragged element, `target` the
+ * `data-paro-drop-target` attribute of the drop zone, both converted via
+ * `FromParoValue`.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParoDrop<Payload, Target = String> {
+    pub payload: Payload,
+    pub target: Target,
+    pub position: DropPosition,
+}
+
+// as send by paro.js
+#[derive(Deserialize)]
+struct RawDrop {
+    payload: String,
+    target: Option<String>,
+    position: DropPosition,
+}
+
+impl <Payload: FromParoValue, Target: FromParoValue> FromParoValue for ParoDrop<Payload, Target> {
+    fn from_paro_value(value: Option<String>) -> Result<Self, String> {
+        let raw: RawDrop = match value {
+            None => return Err("no value".to_owned()),
+            Some(value) => serde_json::from_str(&value)
+                .map_err(|error| format!("not a drop, use on_drop for data-paro-drop: {}", error))?,
+        };
+        Ok(ParoDrop {
+            payload: Payload::from_paro_value(Some(raw.payload))?,
+            target: Target::from_paro_value(raw.target)?,
+            position: raw.position,
+        })
+    }
+}
+
+
+/**
+ * Makes an element a drop zone for elements with a `data-paro-drag` attribute.
+ * Put the returned value into its `data-paro-drop` attribute. `positions` are
+ * the drop positions the zone accepts, paro.js picks one depending on where
+ * the element is dropped and highlights the zone meanwhile with the
+ * `paro-drop-before`, `paro-drop-after` or `paro-drop-inside` class. The
+ * dragged element has the `paro-dragging` class.
+ *
+ * ```ignore
+ * @for employee in &state.employees {
+ *     tr draggable="true" data-paro-drag=(employee.id) data-paro-drop-target=(employee.id)
+ *         data-paro-drop=(on_drop(paro_app, &[DropPosition::Before, DropPosition::After],
+ *             |state: &mut ApplicationState, drop: ParoDrop<String>, _ctx: &mut ParoContext<ApplicationState>| {
+ *                 state.move_employee(&drop.payload, &drop.target, drop.position)
+ *             })) { /* .. */ }
+ * }
+ * ```
+ */
+pub fn on_drop<State, Payload, Target, Action, Callback>(
+    paro_app: &Arc<RwLock<ParoApp<State>>>,
+    positions: &[DropPosition],
+    callback: Callback,
+) -> String
+where
+    State: Send + Sync + 'static,
+    Payload: FromParoValue,
+    Target: FromParoValue,
+    Action: IntoParoAction,
+    Callback: FnMut(&mut State, ParoDrop<Payload, Target>, &mut ParoContext<State>) -> Action + Send + Sync + 'static,
+{
+    let id = Uuid::new_v4().to_string();
+    let positions: Vec<&str> = positions.iter().map(|position| position.as_str()).collect();
+    register_with_context(paro_app, id.clone(), false, callback);
+    format!("{}:{}", id, positions.join(","))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{Json, ParoError};
+    use crate::tests::{collecting_errors, wait_for_callback};
+
+    fn parse<Payload: FromParoValue, Target: FromParoValue>(value: &str) -> Result<ParoDrop<Payload, Target>, String> {
+        ParoDrop::from_paro_value(Some(value.to_owned()))
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Card {
+        id: u32,
+        title: String,
+    }
+
+    #[test]
+    fn parses_payload_target_and_position() {
+        assert_eq!(parse(r#"{"payload":"anna","target":"7","position":"before"}"#), Ok(ParoDrop {
+            payload: "anna".to_owned(),
+            target: 7u32,
+            position: DropPosition::Before,
+        }));
+        // a drop zone without data-paro-drop-target
+        assert_eq!(parse(r#"{"payload":"anna","target":null,"position":"inside"}"#), Ok(ParoDrop {
+            payload: "anna".to_owned(),
+            target: None::<String>,
+            position: DropPosition::Inside,
+        }));
+    }
+
+    #[test]
+    fn parses_json_payloads() {
+        let drop = parse::<Json<Card>, String>(r#"{"payload":"{\"id\":3,\"title\":\"Review\"}","target":"done","position":"after"}"#);
+        assert_eq!(drop.map(|drop| drop.payload.0), Ok(Card { id: 3, title: "Review".to_owned() }));
+        assert!(parse::<Json<Card>, String>(r#"{"payload":"{\"id\":\"3\"}","target":"done","position":"after"}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_or_unknown_positions() {
+        assert!(parse::<String, String>(r#"{"payload":"anna","target":"7"}"#).unwrap_err().contains("use on_drop"));
+        assert!(parse::<String, String>(r#"{"payload":"anna","target":"7","position":"above"}"#).is_err());
+        assert!(parse::<String, String>("anna").is_err());
+        assert_eq!(ParoDrop::<String>::from_paro_value(None), Err("no value".to_owned()));
+    }
+
+    #[test]
+    fn invalid_payloads_go_to_the_error_handler() {
+        let paro_app = Arc::new(RwLock::new(collecting_errors()));
+        let dropped = on_drop(&paro_app, &[DropPosition::Inside], |errors: &mut Vec<ParoError>, _: ParoDrop<u32>, _: &mut ParoContext<Vec<ParoError>>| {
+            errors.clear();
+        });
+        assert!(dropped.ends_with(":inside"));
+        let id = dropped.split(':').next().unwrap();
+        wait_for_callback(&paro_app, id);
+
+        let mut app = paro_app.write().unwrap();
+        let value = r#"{"payload":"anna","target":"list","position":"inside"}"#;
+        assert_eq!(app.call(format!("{}__PARO__{}", id, value)), Ok(()));
+        assert!(matches!(&app.state[..], [ParoError::InvalidValue { message, .. }] if message.contains("could not parse 'anna'")));
+        // the callback was not called, it would have cleared the errors
+        let value = r#"{"payload":"7","target":"list","position":"inside"}"#;
+        assert_eq!(app.call(format!("{}__PARO__{}", id, value)), Ok(()));
+        assert!(app.state.is_empty());
+    }
+}
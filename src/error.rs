@@ -0,0 +1,76 @@
+use std::fmt;
+
+
+/**
+ * Everything that can go wrong when pâro calls one of your callbacks.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParoError {
+    /// There is no callback registered for the given id. Usually the callback
+    /// was cleared by `ParoApp::iterate` and the client still showed old html.
+    CallbackNotFound(String),
+    /// The callback is a one-shot callback and was already called, for example
+    /// because of a double click. It was not called again.
+    AlreadyConsumed(String),
+    /// The value send by the client could not be parsed into the type the
+    /// callback expects. The callback was not called.
+    InvalidValue {
+        callback_id: String,
+        value: Option<String>,
+        message: String,
+    },
+    /// A file upload was rejected, for example because the file is larger than
+    /// allowed. The upload callback was not called.
+    UploadRejected {
+        name: String,
+        reason: String,
+    },
+    /// paro.js send a message that is not an event and could not be understood,
+    /// usually because paro.js and pâro do not have the same version.
+    InvalidMessage {
+        message: String,
+        error: String,
+    },
+    /// A value stored via `ParoStorage` is not of the type it was read as.
+    /// It was read as `None`.
+    InvalidStoredValue {
+        key: String,
+        message: String,
+    },
+    /// A value could not be written to the storage of the webview, as paro.js
+    /// is not connected. It is only kept until the app restarts.
+    StorageNotWritten(String),
+    /// A dialog could not be closed, as it is not open (anymore).
+    DialogNotOpen(String),
+    /// The keys of a `Hotkey` are not a valid shortcut, see `Hotkey::try_new`.
+    InvalidHotkey {
+        keys: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ParoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParoError::CallbackNotFound(id) => write!(f, "[paro] callback '{}' not found", id),
+            ParoError::AlreadyConsumed(id) => write!(f, "[paro] one-shot callback '{}' was already called", id),
+            ParoError::InvalidValue { callback_id, value, message } => write!(
+                f,
+                "[paro] callback '{}' received invalid value {:?}: {}",
+                callback_id, value, message
+            ),
+            ParoError::UploadRejected { name, reason } => write!(f, "[paro] upload of '{}' was rejected: {}", name, reason),
+            ParoError::InvalidMessage { message, error } => write!(
+                f,
+                "[paro] could not understand message {:?} from paro.js: {}",
+                message, error
+            ),
+            ParoError::InvalidStoredValue { key, message } => write!(f, "[paro] stored value of '{}' has an unexpected type: {}", key, message),
+            ParoError::StorageNotWritten(key) => write!(f, "[paro] paro.js is not connected, '{}' is only stored until the app restarts", key),
+            ParoError::DialogNotOpen(id) => write!(f, "[paro] could not close dialog '{}', it is not open", id),
+            ParoError::InvalidHotkey { keys, message } => write!(f, "[paro] invalid hotkey '{}': {}", keys, message),
+        }
+    }
+}
+
+impl std::error::Error for ParoError {}
@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+
+/**
+ * How event! references your callbacks in the html.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventMode {
+    /// event! returns `window.__PARO__.emitEvent(...)` to be put into
+    /// `onclick`, `oninput`, etc. This is the default.
+    Inline,
+    /// event! returns only the callback id to be put into `data-paro-click`,
+    /// `data-paro-input`, etc. paro.js listens for those events on the paro
+    /// element, so no inline javascript is needed and your app can use a
+    /// Content-Security-Policy without `unsafe-inline`.
+    Delegated,
+}
+
+// the mode is global, as event! is called while the ParoApp might be locked
+static EVENT_MODE: AtomicU8 = AtomicU8::new(0);
+
+/**
+ * Sets how event! references your callbacks. Set it once before rendering.
+ */
+pub fn set_event_mode(mode: EventMode) {
+    let value = match mode {
+        EventMode::Inline => 0,
+        EventMode::Delegated => 1,
+    };
+    EVENT_MODE.store(value, Ordering::Relaxed);
+}
+
+pub fn event_mode() -> EventMode {
+    match EVENT_MODE.load(Ordering::Relaxed) {
+        0 => EventMode::Inline,
+        _ => EventMode::Delegated,
+    }
+}
+
+/**
+ * The attribute the result of event! goes into for the given event, like
+ * `onclick` or `data-paro-click`. For html pâro renders itself.
+ */
+pub(crate) fn event_attribute(event: &str) -> String {
+    match event_mode() {
+        EventMode::Inline => format!("on{}", event),
+        EventMode::Delegated => format!("data-paro-{}", event),
+    }
+}
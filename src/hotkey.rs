@@ -0,0 +1,205 @@
+use std::sync::{Arc, RwLock};
+
+use uuid::Uuid;
+
+use crate::{IntoParoAction, ParoApp, ParoContext, ParoError};
+use crate::__private::register_with_context;
+use crate::render::escape_html;
+
+
+type HotkeyCallback<State, Context> = Box<dyn FnMut(&mut State, &mut ParoContext<Context>) + Send + Sync + 'static>;
+
+
+/**
+ * A keyboard shortcut, active as long as the html returned by `hotkey` is on
+ * the page. Keys are written like `ctrl+s`, `shift+alt+ArrowUp` or `Escape`,
+ * the key being `KeyboardEvent.key`. Modifiers are `ctrl`, `shift`, `alt`
+ * and `meta`.
+ *
+ * Shortcuts without ctrl, alt or meta are ignored while the user types into
+ * an input, except for Escape. If several shortcuts match, the one with the
+ * highest priority is called, scoped ones before global ones.
+ *
+ * The callback receives the context of the application, whose state is
+ * `Context`. That is the state itself, unless the hotkey belongs to a
+ * component, see `ComponentScope::hotkey`.
+ */
+pub struct Hotkey<State, Context = State> {
+    keys: String,
+    description: String,
+    scope: Option<String>,
+    priority: i32,
+    callback: HotkeyCallback<State, Context>,
+}
+
+impl <State: 'static, Context: 'static> Hotkey<State, Context> {
+    /**
+     * `description` is shown in the list of shortcuts paro.js shows when `?`
+     * is pressed.
+     *
+     * Panics if `keys` is not a valid shortcut, meant for keys written in the
+     * code. Use `try_new` for keys the user configured.
+     */
+    pub fn new<Action, Callback>(keys: &str, description: &str, callback: Callback) -> Hotkey<State, Context>
+    where
+        Action: IntoParoAction,
+        Callback: FnMut(&mut State, &mut ParoContext<Context>) -> Action + Send + Sync + 'static,
+    {
+        Hotkey::try_new(keys, description, callback).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /**
+     * Like `new`, but returns `ParoError::InvalidHotkey` if `keys` is not a
+     * valid shortcut, for example `ctrl+` or `hyper+s`.
+     */
+    pub fn try_new<Action, Callback>(keys: &str, description: &str, mut callback: Callback) -> Result<Hotkey<State, Context>, ParoError>
+    where
+        Action: IntoParoAction,
+        Callback: FnMut(&mut State, &mut ParoContext<Context>) -> Action + Send + Sync + 'static,
+    {
+        Ok(Hotkey {
+            keys: check_keys(keys)?,
+            description: description.to_owned(),
+            scope: None,
+            priority: 0,
+            callback: Box::new(move |state, context| callback(state, context).apply(context)),
+        })
+    }
+
+    /**
+     * Only active while the focus is inside an element matching the css selector
+     */
+    pub fn scope(mut self, selector: &str) -> Hotkey<State, Context> {
+        self.scope = Some(selector.to_owned());
+        self
+    }
+
+    /**
+     * Decides between shortcuts with the same keys, higher wins. 0 by default.
+     */
+    pub fn priority(mut self, priority: i32) -> Hotkey<State, Context> {
+        self.priority = priority;
+        self
+    }
+
+    // for components, whose hotkeys receive the component instead of the whole state
+    pub(crate) fn lens<Lens>(self, lens: Lens) -> Hotkey<Context>
+    where
+        Lens: for<'a> Fn(&'a mut Context) -> &'a mut State + Send + Sync + 'static,
+    {
+        let mut callback = self.callback;
+        Hotkey {
+            keys: self.keys,
+            description: self.description,
+            scope: self.scope,
+            priority: self.priority,
+            callback: Box::new(move |outer, context| callback(lens(outer), context)),
+        }
+    }
+}
+
+
+/**
+ * Registers a keyboard shortcut and returns the html declaring it to paro.js.
+ * Put it anywhere on the page, the shortcut is active as long as it is
+ * rendered, so each page declares its own shortcuts.
+ *
+ * ```ignore
+ * html! {
+ *     (PreEscaped(hotkey(paro_app, Hotkey::new("ctrl+s", "Save employee",
+ *         |state: &mut ApplicationState, _ctx: &mut ParoContext<ApplicationState>| state.save()))))
+ *     (PreEscaped(hotkey(paro_app, Hotkey::new("Escape", "Cancel editing",
+ *         |state: &mut ApplicationState, _ctx: &mut ParoContext<ApplicationState>| state.page = Page::List).scope("form"))))
+ * }
+ * ```
+ */
+pub fn hotkey<State>(paro_app: &Arc<RwLock<ParoApp<State>>>, hotkey: Hotkey<State>) -> String
+where
+    State: Send + Sync + 'static,
+{
+    let id = Uuid::new_v4().to_string();
+    let mut callback = hotkey.callback;
+    register_with_context(paro_app, id.clone(), false, move |state: &mut State, _value: Option<String>, context: &mut ParoContext<State>| {
+        callback(state, context)
+    });
+    let scope = match &hotkey.scope {
+        Some(scope) => format!(r#" data-paro-hotkey-scope="{}""#, escape_html(scope)),
+        None => "".to_owned(),
+    };
+    format!(
+        r#"<template data-paro-hotkey="{}" data-paro-hotkey-call="{}" data-paro-hotkey-priority="{}" data-paro-hotkey-description="{}"{}></template>"#,
+        escape_html(&hotkey.keys), id, hotkey.priority, escape_html(&hotkey.description), scope
+    )
+}
+
+
+/**
+ * The keys normalized for paro.js, or `ParoError::InvalidHotkey` if they are
+ * not a valid shortcut
+ */
+pub(crate) fn check_keys(keys: &str) -> Result<String, ParoError> {
+    normalize_keys(keys).map_err(|message| ParoError::InvalidHotkey {
+        keys: keys.to_owned(),
+        message,
+    })
+}
+
+// lower case with the modifiers in a fixed order, like paro.js builds them
+fn normalize_keys(keys: &str) -> Result<String, String> {
+    let parts: Vec<String> = keys.split('+').map(|part| part.trim().to_lowercase()).collect();
+    let (key, modifiers) = parts.split_last().ok_or("no key")?;
+    let key = match key.as_str() {
+        "" => return Err("no key".to_owned()),
+        "esc" => "escape",
+        "space" => " ",
+        "plus" => "+",
+        key => key,
+    };
+    let mut found = [false; 4];
+    for modifier in modifiers {
+        let index = match modifier.as_str() {
+            "ctrl" | "control" => 0,
+            "shift" => 1,
+            "alt" | "option" => 2,
+            "meta" | "cmd" | "command" | "super" => 3,
+            other => return Err(format!("unknown modifier '{}', expected ctrl, shift, alt or meta", other)),
+        };
+        found[index] = true;
+    }
+    let mut normalized: Vec<&str> = ["ctrl", "shift", "alt", "meta"].iter()
+        .zip(found)
+        .filter(|(_, found)| *found)
+        .map(|(modifier, _)| *modifier)
+        .collect();
+    normalized.push(key);
+    Ok(normalized.join("+"))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_keys_like_paro_js() {
+        assert_eq!(normalize_keys("ctrl+s"), Ok("ctrl+s".to_owned()));
+        assert_eq!(normalize_keys("Shift + Control + ArrowUp"), Ok("ctrl+shift+arrowup".to_owned()));
+        assert_eq!(normalize_keys("meta+alt+ctrl+shift+K"), Ok("ctrl+shift+alt+meta+k".to_owned()));
+        assert_eq!(normalize_keys("cmd+option+p"), Ok("alt+meta+p".to_owned()));
+        assert_eq!(normalize_keys("Esc"), Ok("escape".to_owned()));
+        assert_eq!(normalize_keys("ctrl+space"), Ok("ctrl+ ".to_owned()));
+        assert_eq!(normalize_keys("ctrl+plus"), Ok("ctrl++".to_owned()));
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        assert!(normalize_keys("").is_err());
+        assert!(normalize_keys("ctrl+").is_err());
+        assert!(normalize_keys("hyper+s").is_err());
+        let hotkey = Hotkey::<()>::try_new("hyper+s", "Save", |_state: &mut (), _ctx: &mut ParoContext<()>| {});
+        assert_eq!(hotkey.err(), Some(ParoError::InvalidHotkey {
+            keys: "hyper+s".to_owned(),
+            message: "unknown modifier 'hyper', expected ctrl, shift, alt or meta".to_owned(),
+        }));
+    }
+}
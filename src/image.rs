@@ -0,0 +1,170 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+
+use crate::{ParoClient, ParoMessage};
+use crate::protocol::ServerMessage;
+
+
+/**
+ * Refers to an image registered via `ParoImages`. Use `src()` as src of an
+ * `<img>` or put the handle into a `data-paro-src` attribute.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageHandle(String);
+
+impl ImageHandle {
+    /**
+     * `paro-image://<handle>`, paro.js replaces it with the image once it arrived
+     */
+    pub fn src(&self) -> String {
+        format!("paro-image://{}", self.0)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ImageHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+
+struct Image {
+    mime: String,
+    bytes: Arc<Vec<u8>>,
+}
+
+
+/**
+ * Images generated in rust, like plots or thumbnails. Each image is send to
+ * paro.js once as a binary websocket message and cached there until you
+ * release it, so rendering the page again only sends the handle instead of
+ * the whole image.
+ *
+ * ```ignore
+ * let plot = paro_app.read().unwrap().images().register("image/png", render_plot(&state));
+ * html! {
+ *     img src=(plot.src()) alt="plot" {}
+ * }
+ * ```
+ *
+ * Registering the same bytes again returns the same handle without sending
+ * them again. Images are send via the messages of `ParoApp::connect`, so
+ * your websocket loop has to forward those.
+ *
+ * Every image stays until it is released, pâro cannot know whether you still
+ * show it. Register images that change with your data via `register_named`,
+ * so each version replaces the previous one.
+ */
+#[derive(Clone)]
+pub struct ParoImages {
+    images: Arc<Mutex<HashMap<String, Image>>>,
+    client: ParoClient,
+}
+
+impl fmt::Debug for ParoImages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParoImages")
+            .field("images", &self.images.lock().unwrap().keys().collect::<Vec<&String>>())
+            .finish()
+    }
+}
+
+impl ParoImages {
+    pub(crate) fn new(client: ParoClient) -> ParoImages {
+        ParoImages {
+            images: Arc::new(Mutex::new(HashMap::new())),
+            client,
+        }
+    }
+
+    /**
+     * Registers the image under a handle derived from its content. It is kept
+     * until you `release` it, even once its content changed.
+     */
+    pub fn register(&self, mime: &str, bytes: Vec<u8>) -> ImageHandle {
+        let mut hasher = DefaultHasher::new();
+        mime.hash(&mut hasher);
+        bytes.hash(&mut hasher);
+        self.register_named(&format!("{:016x}", hasher.finish()), mime, bytes)
+    }
+
+    /**
+     * Registers the image under the given name. If there already is a
+     * different image with that name, it is replaced, on the page as well.
+     */
+    pub fn register_named(&self, name: &str, mime: &str, bytes: Vec<u8>) -> ImageHandle {
+        let handle = ImageHandle(name.to_owned());
+        let mut images = self.images.lock().unwrap();
+        if let Some(image) = images.get(name) {
+            if image.mime == mime && *image.bytes == bytes {
+                return handle;
+            }
+        }
+        let image = Image { mime: mime.to_owned(), bytes: Arc::new(bytes) };
+        self.send(name, &image);
+        images.insert(name.to_owned(), image);
+        handle
+    }
+
+    /**
+     * Removes the image here and from the cache of paro.js
+     */
+    pub fn release(&self, handle: &ImageHandle) {
+        if self.images.lock().unwrap().remove(handle.as_str()).is_some() {
+            self.client.send(ParoMessage::Text(ServerMessage {
+                released_images: vec![handle.as_str().to_owned()],
+                ..ServerMessage::default()
+            }.encode()));
+        }
+    }
+
+    /**
+     * Sends all images again, as a new connection starts with an empty cache
+     */
+    pub(crate) fn send_all(&self) {
+        for (name, image) in self.images.lock().unwrap().iter() {
+            self.send(name, image);
+        }
+    }
+
+    // a line of json describing the image, followed by the image itself
+    fn send(&self, name: &str, image: &Image) {
+        let mut frame = json!({ "image": name, "mime": image.mime }).to_string().into_bytes();
+        frame.push(b'\n');
+        frame.extend_from_slice(&image.bytes);
+        // sent once connected otherwise
+        self.client.send(ParoMessage::Binary(frame));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_images_replace_their_previous_version() {
+        let images = ParoImages::new(ParoClient::default());
+        let first = images.register("image/png", vec![1]);
+        assert_eq!(images.register("image/png", vec![1]), first);
+        assert_ne!(images.register("image/png", vec![2]), first);
+        assert_eq!(images.images.lock().unwrap().len(), 2);
+
+        let chart = images.register_named("chart", "image/svg+xml", vec![1]);
+        assert_eq!(images.register_named("chart", "image/svg+xml", vec![2]), chart);
+        assert_eq!(images.images.lock().unwrap().len(), 3);
+
+        images.release(&first);
+        images.release(&chart);
+        assert_eq!(images.images.lock().unwrap().len(), 1);
+    }
+}
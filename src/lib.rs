@@ -1,7 +1,81 @@
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+
+use dialog::OpenDialog;
+use task::ParoTasks;
+use toast::ParoToasts;
+use protocol::ClientMessage;
+use render::RenderedCallbacks;
+use upload::{SharedUpload, Transfer};
 
 pub use uuid::Uuid;
+pub use paro_rs_macros::{event, event_async, event_once};
+
+mod action;
+mod canvas;
+mod client;
+mod combobox;
+mod command;
+mod component;
+mod context;
+mod dialog;
+mod drag;
+mod error;
+mod event_mode;
+mod hotkey;
+mod image;
+mod memo;
+mod palette;
+mod protocol;
+mod query;
+mod render;
+mod storage;
+mod task;
+mod toast;
+mod upload;
+mod value;
+
+pub use action::*;
+pub use canvas::*;
+pub use client::*;
+pub use combobox::*;
+pub use command::*;
+pub use component::*;
+pub use context::*;
+pub use dialog::{ConfirmDialog, DialogHandle, ParoDialog, PromptDialog};
+pub use drag::*;
+pub use error::*;
+pub use event_mode::*;
+pub use hotkey::*;
+pub use image::*;
+pub use memo::*;
+pub use palette::*;
+pub use query::*;
+pub use render::*;
+pub use storage::*;
+pub use task::ParoAsyncContext;
+pub use toast::{DismissReason, Level, Toast, ToastHandle};
+pub use upload::*;
+pub use value::*;
+
+
+/**
+ * A callback as it is passed to `ParoApp::insert`. It receives your state and
+ * the raw value of the element that triggered the event, if there is any.
+ */
+pub type ParoCallback<State> = Arc<RwLock<dyn FnMut(&mut State, Option<String>) + Send + Sync + 'static>>;
+
+/**
+ * Receives errors that occur while pâro calls your callbacks, for example
+ * values that could not be parsed into the type a callback expects.
+ */
+pub type ParoErrorHandler<State> = Box<dyn FnMut(&mut State, &ParoError) + Send + Sync + 'static>;
+
+// callbacks as stored by ParoApp. Err contains the reason the value could not be parsed.
+type StoredCallback<State> = Arc<RwLock<dyn FnMut(&mut State, &mut ParoContext<State>, Option<String>) -> Result<(), String> + Send + Sync + 'static>>;
+
+// renders the content of a region, see render_region
+pub(crate) type RegionRenderer<State> = Arc<dyn Fn(&mut Arc<RwLock<ParoApp<State>>>) -> String + Send + Sync + 'static>;
 
 
 /**
@@ -9,129 +83,804 @@ pub use uuid::Uuid;
  * callback store that holds all your server side callbacks / eventhandlers.
  */
  pub struct ParoApp<State> {
-    callbacks: HashMap<String, (u128, Arc<RwLock<dyn FnMut(&mut State, Option<String>) + Send + Sync + 'static>>)>,
+    callbacks: HashMap<String, (u128, StoredCallback<State>)>,
+    one_shot: HashSet<String>,
+    // upload handlers by callback id, see upload
+    uploads: HashMap<String, (u128, SharedUpload<State>)>,
+    // files that are being received, by the id paro.js gave them
+    transfers: HashMap<String, Transfer<State>>,
+    // ids of files that were received or rejected since the last render, so paro.js stops showing them as pending
+    finished_uploads: Vec<String>,
+    // one-shot callbacks that were already called and the iteration they were called in
+    consumed: HashMap<String, u128>,
     iteration: u128,
+    error_handler: Option<ParoErrorHandler<State>>,
+    context: ParoContext<State>,
+    // behind a mutex, as regions are registered while rendering, when we only have a read lock
+    regions: Mutex<HashMap<String, RegionRenderer<State>>>,
+    memos: Mutex<HashMap<String, MemoEntry>>,
+    // the callbacks paro.js currently shows, see render_update
+    rendered: Mutex<RenderedCallbacks>,
+    // open dialogs, the last one on top, see ParoDialog
+    dialogs: Vec<OpenDialog<State>>,
+    // whether dialogs were opened or closed since the last render
+    dialogs_changed: bool,
+    // async callbacks waiting for something, see event_async!
+    tasks: ParoTasks<State>,
+    // toasts shown above the page and those waiting for room, see Toast
+    toasts: ParoToasts<State>,
     pub state: State,
 }
 
-impl <State> ParoApp<State> {
+impl <State: 'static> ParoApp<State> {
     pub fn new(state: State) -> ParoApp<State> {
+        let context = ParoContext::new();
         ParoApp::<State> {
             callbacks: HashMap::new(),
+            one_shot: HashSet::new(),
+            uploads: HashMap::new(),
+            transfers: HashMap::new(),
+            finished_uploads: vec![],
+            consumed: HashMap::new(),
             iteration: 0,
-            state: state,
+            error_handler: None,
+            tasks: ParoTasks::new(context.client()),
+            context,
+            regions: Mutex::new(HashMap::new()),
+            memos: Mutex::new(HashMap::new()),
+            rendered: Mutex::new(RenderedCallbacks::default()),
+            dialogs: vec![],
+            dialogs_changed: false,
+            toasts: ParoToasts::new(),
+            state,
         }
     }
 
     /**
      * Register a callback with pâro so it can be called by it
      */
-    pub fn insert(&mut self, id: String, callback: Arc<RwLock<dyn FnMut(&mut State, Option<String>) + Send + Sync + 'static>>) -> () {
+    pub fn insert(&mut self, id: String, callback: ParoCallback<State>) {
+        self.insert_stored(id, Arc::new(RwLock::new(move |state: &mut State, _context: &mut ParoContext<State>, value: Option<String>| {
+            let mut locked = callback.write().unwrap();
+            locked(state, value);
+            Ok(())
+        })));
+    }
+
+    /**
+     * Register a callback that receives the value of the element parsed into
+     * any type implementing `FromParoValue`, for example `u64`, `bool` or
+     * `Json<MyStruct>`. If the value cannot be parsed, the callback is not
+     * called and the error is passed to the error handler instead. The callback
+     * can return a `ParoAction` to tell pâro what to do after it ran.
+     */
+    pub fn insert_typed<Value, Action, Callback>(&mut self, id: String, mut callback: Callback)
+    where
+        Value: FromParoValue,
+        Action: IntoParoAction,
+        Callback: FnMut(&mut State, Value) -> Action + Send + Sync + 'static,
+    {
+        self.insert_stored(id, Arc::new(RwLock::new(move |state: &mut State, context: &mut ParoContext<State>, value: Option<String>| {
+            let value = Value::from_paro_value(value)?;
+            callback(state, value).apply(context);
+            Ok(())
+        })));
+    }
+
+    /**
+     * Like `insert_typed`, but the callback additionally receives the `ParoContext`
+     * to tell pâro what to do after the callback ran.
+     */
+    pub fn insert_with_context<Value, Action, Callback>(&mut self, id: String, mut callback: Callback)
+    where
+        Value: FromParoValue,
+        Action: IntoParoAction,
+        Callback: FnMut(&mut State, Value, &mut ParoContext<State>) -> Action + Send + Sync + 'static,
+    {
+        self.insert_stored(id, Arc::new(RwLock::new(move |state: &mut State, context: &mut ParoContext<State>, value: Option<String>| {
+            let value = Value::from_paro_value(value)?;
+            let action = callback(state, value, context);
+            action.apply(context);
+            Ok(())
+        })));
+    }
+
+    /**
+     * Register an async callback. It receives a `ParoAsyncContext` instead of
+     * the state and runs whenever pâro handled an event, until it is done.
+     * event_async! uses this.
+     */
+    pub fn insert_async<Value, Callback, Task>(&mut self, id: String, mut callback: Callback)
+    where
+        Value: FromParoValue,
+        Callback: FnMut(ParoAsyncContext<State>, Value) -> Task + Send + Sync + 'static,
+        Task: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let context = self.tasks.context();
+        let start = self.tasks.starter();
+        self.insert_stored(id, Arc::new(RwLock::new(move |_state: &mut State, _context: &mut ParoContext<State>, value: Option<String>| {
+            let value = Value::from_paro_value(value)?;
+            start(callback(context.clone(), value));
+            Ok(())
+        })));
+    }
+
+    /**
+     * Register a callback that can only be called once, like saving or deleting
+     * something. It is removed on its first call and further calls report
+     * `ParoError::AlreadyConsumed`. A call with a value that cannot be parsed
+     * does not count, the callback can still be called. event_once! uses this.
+     */
+    pub fn insert_once<Value, Action, Callback>(&mut self, id: String, callback: Callback)
+    where
+        Value: FromParoValue,
+        Action: IntoParoAction,
+        Callback: FnOnce(&mut State, Value) -> Action + Send + Sync + 'static,
+    {
+        let mut callback = Some(callback);
+        self.make_one_shot(&id);
+        self.insert_stored(id, Arc::new(RwLock::new(move |state: &mut State, context: &mut ParoContext<State>, value: Option<String>| {
+            let value = Value::from_paro_value(value)?;
+            if let Some(callback) = callback.take() {
+                callback(state, value).apply(context);
+            }
+            Ok(())
+        })));
+    }
+
+    /**
+     * Like `insert_once`, but the callback additionally receives the `ParoContext`
+     */
+    pub fn insert_once_with_context<Value, Action, Callback>(&mut self, id: String, callback: Callback)
+    where
+        Value: FromParoValue,
+        Action: IntoParoAction,
+        Callback: FnOnce(&mut State, Value, &mut ParoContext<State>) -> Action + Send + Sync + 'static,
+    {
+        let mut callback = Some(callback);
+        self.make_one_shot(&id);
+        self.insert_stored(id, Arc::new(RwLock::new(move |state: &mut State, context: &mut ParoContext<State>, value: Option<String>| {
+            let value = Value::from_paro_value(value)?;
+            if let Some(callback) = callback.take() {
+                let action = callback(state, value, context);
+                action.apply(context);
+            }
+            Ok(())
+        })));
+    }
+
+    fn insert_stored(&mut self, id: String, callback: StoredCallback<State>) {
         if self.callbacks.contains_key(&id) {
             panic!("[paro] callback ids must be unique, '{}' is not", &id);
         }
         self.callbacks.insert(id, (self.iteration, callback));
     }
 
+    /**
+     * Only allow the callback to be called once. It is removed on its first call
+     * and further calls, for example from a double click on a save button, report
+     * `ParoError::AlreadyConsumed`. event! does this for you if you use the `once`
+     * modifier.
+     */
+    pub fn make_one_shot(&mut self, id: &str) {
+        self.one_shot.insert(id.to_owned());
+    }
+
+    /**
+     * Set a handler for errors that occur while calling callbacks, like
+     * values that could not be parsed. Without an error handler `call`
+     * returns those errors to you. Errors that occur outside of `call`, like
+     * while rendering, are passed to the handler once pâro handles the next
+     * event or renders. Without a handler the next `call` returns them.
+     */
+    pub fn set_error_handler<Handler>(&mut self, handler: Handler)
+    where
+        Handler: FnMut(&mut State, &ParoError) + Send + Sync + 'static,
+    {
+        self.error_handler = Some(Box::new(handler));
+    }
+
     /**
      * Clears old callbacks from the registry. It is adviced to call iterate before
-     * each re-rendering.
+     * each re-rendering. Callbacks of memoized fragments that are still in use are kept,
+     * as are those outside of the regions `render_update` rendered again.
      */
-    pub fn iterate(&mut self) -> () {
+    pub fn iterate(&mut self) {
+        // nothing will be rendered, so the callbacks on the page stay in use
+        if self.context.skips_render() {
+            return;
+        }
         self.iteration += 1;
+        let iteration = self.iteration;
+        // still shown by paro.js, so they only start to age once they are gone from the page
+        for id in self.rendered.get_mut().unwrap().ids() {
+            if let Some((callback_iteration, _callback)) = self.callbacks.get_mut(id) {
+                *callback_iteration = iteration;
+            }
+            if let Some((upload_iteration, _upload)) = self.uploads.get_mut(id) {
+                *upload_iteration = iteration;
+            }
+        }
+        let memos = self.memos.get_mut().unwrap();
+        memos.retain(|_key, entry| iteration - entry.last_used <= 100);
+        let memoized_ids: HashSet<&String> = memos.values()
+            .flat_map(|entry| entry.callback_ids.iter())
+            .collect();
         let keys_to_drop: Vec<String> = {self.callbacks.iter()
-            .filter(|(_key, (callback_iteration, _callback))| self.iteration - callback_iteration > 100)
+            .filter(|(key, (callback_iteration, _callback))| iteration - callback_iteration > 100 && !memoized_ids.contains(key))
             .map(|(key, _value)| key.to_owned())
             .collect()};
         for key in &keys_to_drop {
             self.callbacks.remove(key);
+            self.one_shot.remove(key);
         }
+        self.uploads.retain(|key, (upload_iteration, _upload)| iteration - *upload_iteration <= 100 || memoized_ids.contains(key));
+        self.consumed.retain(|_key, consumed_iteration| iteration - *consumed_iteration <= 100);
         // println!("paro iterate dropped {} old callbacks and now contains {}", keys_to_drop.len(), self.callbacks.len());
     }
 
     /**
-     * Call a callback by its id
+     * Call a callback by its id. Pass every message paro.js sends, except for
+     * ping, messages that are not events like replies to queries are handled as well.
      */
-    pub fn call(&mut self, id: String) -> Result<(), String> {
+    pub fn call(&mut self, id: String) -> Result<(), ParoError> {
+        let result = self.call_callback(id);
+        self.handle_reported_errors();
+        result?;
+        // the oldest error reported outside of callbacks, if there is no handler for them
+        let mut reported = self.context.client().take_errors().into_iter();
+        match reported.next() {
+            Some(error) => {
+                reported.for_each(|error| self.context.client().report(error));
+                Err(error)
+            },
+            None => Ok(()),
+        }
+    }
+
+    fn call_callback(&mut self, id: String) -> Result<(), ParoError> {
         let split = id.split_once("__PARO__")
             .expect("expected __PARO__ as part of the message");
         let id = split.0;
         let value = split.1.to_owned();
-        let value_opt = if value.len() == 0 || value == "undefined" || value == "null" { None} else { Some(value.to_owned()) };
-        match self.callbacks.get(id) {
-            Some((_, callback)) => {
-                let mut locked = { callback.write().unwrap() };
-                let result = Ok(locked(&mut self.state, value_opt));
-                return result;
+        if id.is_empty() {
+            return self.handle_client_message(&value);
+        }
+        let value_opt = if value.is_empty() || value == "undefined" || value == "null" { None} else { Some(value.to_owned()) };
+        if self.consumed.contains_key(id) {
+            return self.handle_error(ParoError::AlreadyConsumed(id.to_owned()));
+        }
+        let callback = match self.callbacks.get(id) {
+            Some((_, callback)) => callback.clone(),
+            None => return Err(ParoError::CallbackNotFound(id.to_owned()))
+        };
+        let result = {
+            let mut locked = { callback.write().unwrap() };
+            locked(&mut self.state, &mut self.context, value_opt.clone())
+        };
+        match result {
+            Ok(()) => {
+                // only consumed once it ran, an invalid value leaves it to be called again
+                if self.one_shot.remove(id) {
+                    self.consumed.insert(id.to_owned(), self.iteration);
+                    self.callbacks.remove(id);
+                    // the memoized html would still point at the consumed callback
+                    self.memos.get_mut().unwrap().retain(|_key, entry| !entry.callback_ids.iter().any(|callback_id| callback_id == id));
+                }
+                Ok(())
+            },
+            Err(message) => self.handle_error(ParoError::InvalidValue {
+                callback_id: id.to_owned(),
+                value: value_opt,
+                message,
+            }),
+        }
+    }
+
+    /**
+     * The `ParoContext` callbacks receive. Use it to mark regions dirty from
+     * outside of a callback, for example when a background task finished.
+     */
+    pub fn context(&mut self) -> &mut ParoContext<State> {
+        &mut self.context
+    }
+
+    /**
+     * Starts a new connection to paro.js, ending the previous one, and returns
+     * the messages pâro wants to send on its own, like queries. Call it for each
+     * websocket connection, before rendering the first page, and forward the
+     * messages to the websocket along with your rendered html. Pass the token
+     * to `disconnect` once the websocket is closed.
+     */
+    pub fn connect(&mut self) -> (ParoReceiver, ConnectionToken) {
+        self.end_connection();
+        // paro.js starts with nothing, so the next render_update renders the whole page
+        self.context.take_dirty_regions();
+        self.context.take_skip_render();
+        let (receiver, connection) = self.context.client().connect();
+        self.context.images().send_all();
+        (receiver, connection)
+    }
+
+    /**
+     * Ends the connection to paro.js. Uploads are aborted, async callbacks
+     * are dropped and dialogs and toasts closed, without calling back the
+     * ones that opened them. Call it once the websocket is closed.
+     *
+     * Does nothing if `connect` was called again since, so a websocket that
+     * closes late does not end the session of the new one.
+     */
+    pub fn disconnect(&mut self, connection: ConnectionToken) {
+        if self.context.client().is_connected(connection) {
+            self.end_connection();
+        }
+    }
+
+    fn end_connection(&mut self) {
+        self.abort_uploads();
+        self.tasks.cancel();
+        if !self.dialogs.is_empty() {
+            self.dialogs.clear();
+            self.dialogs_changed = true;
+        }
+        self.toasts.clear();
+        self.context.client().disconnect();
+    }
+
+    /**
+     * Lets you ask paro.js about things only the client knows, see `ParoClient`
+     */
+    pub fn client(&self) -> ParoClient {
+        self.context.client()
+    }
+
+    /**
+     * Values in the `localStorage` of the webview, see `ParoStorage`
+     */
+    pub fn storage(&self) -> ParoStorage {
+        self.context.storage()
+    }
+
+    /**
+     * Values in the `sessionStorage` of the webview, see `ParoStorage`
+     */
+    pub fn session_storage(&self) -> ParoStorage {
+        self.context.session_storage()
+    }
+
+    /**
+     * Images generated in rust, see `ParoImages`
+     */
+    pub fn images(&self) -> ParoImages {
+        self.context.images()
+    }
+
+    /**
+     * Draws on the `<canvas data-paro-canvas="<name>">`, see `ParoCanvas`
+     */
+    pub fn canvas(&self, name: &str) -> ParoCanvas {
+        self.context.canvas(name)
+    }
+
+    fn handle_client_message(&mut self, message: &str) -> Result<(), ParoError> {
+        match serde_json::from_str::<ClientMessage>(message) {
+            Ok(ClientMessage::Reply { id, value, error }) => {
+                // nothing visible changed
+                self.context.apply(ParoAction::NoRender);
+                let reply = match error {
+                    Some(error) => Err(QueryError::Client(error)),
+                    None => Ok(value),
+                };
+                self.context.client().resolve(id, reply);
+                Ok(())
+            },
+            Ok(ClientMessage::UploadStart { callback, upload, name, mime, size }) =>
+                self.start_upload(&callback, upload, FileInfo { name, mime, size }),
+            Ok(ClientMessage::UploadChunk { upload, data }) => self.receive_chunk(&upload, &data),
+            Ok(ClientMessage::UploadEnd { upload }) => self.finish_upload(&upload),
+            // the page is rendered again, as it may look different with the stored values
+            Ok(ClientMessage::Hello { local, session }) => {
+                self.context.storage().load(local);
+                self.context.session_storage().load(session);
+                Ok(())
+            },
+            Ok(ClientMessage::DismissDialog { dialog }) => {
+                self.dismiss_dialog(&dialog);
+                Ok(())
             },
-            None => Err(format!("[paro] callback '{}' not found", &id))
+            Ok(ClientMessage::DismissToast { toast, reason }) => {
+                self.toast_dismissed(&toast, reason);
+                Ok(())
+            },
+            Err(error) => {
+                self.context.apply(ParoAction::NoRender);
+                self.handle_error(ParoError::InvalidMessage {
+                    message: message.to_owned(),
+                    error: error.to_string(),
+                })
+            },
+        }
+    }
+
+    /**
+     * Passes the errors reported outside of callbacks to the error handler,
+     * if there is one
+     */
+    pub(crate) fn handle_reported_errors(&mut self) {
+        if let Some(handler) = self.error_handler.as_mut() {
+            for error in self.context.client().take_errors() {
+                handler(&mut self.state, &error);
+            }
+        }
+    }
+
+    fn handle_error(&mut self, error: ParoError) -> Result<(), ParoError> {
+        match self.error_handler.as_mut() {
+            Some(handler) => {
+                handler(&mut self.state, &error);
+                Ok(())
+            },
+            None => Err(error),
         }
     }
 }
 
 
+#[doc(hidden)]
+pub mod __private {
+    use std::sync::{Arc, RwLock};
 
-/***
- * Creates a mew event / callback that you can reference in your html.
- * The event / callback is executed inside your tauri app / the server
- * and event! returns a minimal javascript call to tell pâro which
- * event / callback should be triggered on the server side. What you
- * write is pure rust, pâro handles javscript.
- * 
- * Example usage without maud templates
-     let html = format!(
-        r#"<button onclick="{}">
-            counter: {}
-        </button>"#,
-            event!(paro_app, (move |state: &mut ApplicationState, _| { // ApplicationState beeing whatever struct you use, here ParoApp<ApplicationState>
-                // this is executed here in tauri and not in the gui client application
-                state.current_count += 1;
-                println!("first number of state.numbers updated to: {}", state.current_count);
-            })),
-            paro_app.read().unwrap().state.current_count
-        );
- * 
- * Example usage with maud templates:
- * 
-    let maud_template = html! {
-        button onclick=({
-            event!(paro_app, (move |state: &mut ApplicationState, _| {
-                // this is executed here in tauri and not in the gui client application
-                state.current_count += 1;
-                println!("first number of state.numbers updated to: {}", state.current_count);
-            }))
-        }) { "counter:" (paro_app.read().unwrap().state.current_count) }
-    };
-    let html = maud_template.into_string();
- */
-#[macro_export]
-macro_rules! event {
-    ($paroApp:expr, $closure:tt)=>{
-        {
-            let callback_id = paro_rs::Uuid::new_v4().to_string();
-
-            let javascript_call = format!{"window.__PARO__.emitEvent(`{}`, event)", &callback_id};
-            let mut my_paro = $paroApp.clone();
-
-            std::thread::spawn(move || {
-                // TODO: either get rid of this or move it into a proper function
-                loop {
-                    match my_paro.try_write() {
-                        Ok(ref mut data) => {
-                            data.insert(
-                                callback_id.clone(),
-                                Arc::new(RwLock::new(($closure)))
-                            );
-                            break;
-                        }
-                        _ => {
-                            // println!("waiting for lock");
-                            std::thread::sleep(std::time::Duration::from_millis(1));
-                        },
+    use crate::{event_mode, EventMode, FromParoValue, IntoParoAction, ParoApp, ParoAsyncContext, ParoContext};
+    use crate::memo::record_callback;
+
+    /**
+     * Used by event! to register a callback. Rendering usually holds a read lock on
+     * the ParoApp, so the callback is inserted from another thread once it is released.
+     * one_shot is set for the `once` modifier, see `ParoApp::make_one_shot`.
+     */
+    pub fn register<State, Value, Action, Callback>(paro_app: &Arc<RwLock<ParoApp<State>>>, id: String, one_shot: bool, callback: Callback)
+    where
+        State: Send + Sync + 'static,
+        Value: FromParoValue,
+        Action: IntoParoAction,
+        Callback: FnMut(&mut State, Value) -> Action + Send + Sync + 'static,
+    {
+        record_callback(&id);
+        insert_later(paro_app, move |data| {
+            if one_shot {
+                data.make_one_shot(&id);
+            }
+            data.insert_typed(id, callback);
+        });
+    }
+
+    /**
+     * Used by event! to register a callback that takes the `ParoContext` as third argument
+     */
+    pub fn register_with_context<State, Value, Action, Callback>(paro_app: &Arc<RwLock<ParoApp<State>>>, id: String, one_shot: bool, callback: Callback)
+    where
+        State: Send + Sync + 'static,
+        Value: FromParoValue,
+        Action: IntoParoAction,
+        Callback: FnMut(&mut State, Value, &mut ParoContext<State>) -> Action + Send + Sync + 'static,
+    {
+        record_callback(&id);
+        insert_later(paro_app, move |data| {
+            if one_shot {
+                data.make_one_shot(&id);
+            }
+            data.insert_with_context(id, callback);
+        });
+    }
+
+    /**
+     * Used by event_async! to register an async callback
+     */
+    pub fn register_async<State, Value, Callback, Task>(paro_app: &Arc<RwLock<ParoApp<State>>>, id: String, one_shot: bool, callback: Callback)
+    where
+        State: Send + Sync + 'static,
+        Value: FromParoValue,
+        Callback: FnMut(ParoAsyncContext<State>, Value) -> Task + Send + Sync + 'static,
+        Task: std::future::Future<Output = ()> + Send + 'static,
+    {
+        record_callback(&id);
+        insert_later(paro_app, move |data| {
+            if one_shot {
+                data.make_one_shot(&id);
+            }
+            data.insert_async(id, callback);
+        });
+    }
+
+    /**
+     * Used by event_once! to register a callback that can only be called once
+     */
+    pub fn register_once<State, Value, Action, Callback>(paro_app: &Arc<RwLock<ParoApp<State>>>, id: String, callback: Callback)
+    where
+        State: Send + Sync + 'static,
+        Value: FromParoValue,
+        Action: IntoParoAction,
+        Callback: FnOnce(&mut State, Value) -> Action + Send + Sync + 'static,
+    {
+        record_callback(&id);
+        insert_later(paro_app, move |data| data.insert_once(id, callback));
+    }
+
+    /**
+     * Used by event_once! to register a callback that can only be called once and
+     * takes the `ParoContext` as third argument
+     */
+    pub fn register_once_with_context<State, Value, Action, Callback>(paro_app: &Arc<RwLock<ParoApp<State>>>, id: String, callback: Callback)
+    where
+        State: Send + Sync + 'static,
+        Value: FromParoValue,
+        Action: IntoParoAction,
+        Callback: FnOnce(&mut State, Value, &mut ParoContext<State>) -> Action + Send + Sync + 'static,
+    {
+        record_callback(&id);
+        insert_later(paro_app, move |data| data.insert_once_with_context(id, callback));
+    }
+
+    pub(crate) fn insert_later<State, Insert>(paro_app: &Arc<RwLock<ParoApp<State>>>, insert: Insert)
+    where
+        State: Send + Sync + 'static,
+        Insert: FnOnce(&mut ParoApp<State>) + Send + 'static,
+    {
+        let paro_app = paro_app.clone();
+        std::thread::spawn(move || {
+            loop {
+                match paro_app.try_write() {
+                    Ok(mut data) => {
+                        insert(&mut data);
+                        break;
                     }
-                }    
+                    _ => {
+                        std::thread::sleep(std::time::Duration::from_millis(1));
+                    },
+                }
+            }
+        });
+    }
+
+    /**
+     * What event! returns to be put into your html, depending on the `EventMode`.
+     * modifiers are encoded by event! like `prevent,key=Enter`.
+     */
+    pub fn emit_event_call(id: &str, modifiers: &str) -> String {
+        match (event_mode(), modifiers.is_empty()) {
+            (EventMode::Inline, true) => format!("window.__PARO__.emitEvent(`{}`, event)", id),
+            (EventMode::Inline, false) => format!("window.__PARO__.emitEvent(`{}`, event, undefined, `{}`)", id, modifiers),
+            (EventMode::Delegated, true) => id.to_owned(),
+            (EventMode::Delegated, false) => format!("{}:{}", id, modifiers),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the callbacks of event!, upload and alike are inserted by another thread once rendering is done
+    pub(crate) fn wait_for_callback<State>(paro_app: &Arc<RwLock<ParoApp<State>>>, id: &str) {
+        for _ in 0..5000 {
+            let inserted = {
+                let app = paro_app.read().unwrap();
+                app.callbacks.contains_key(id) || app.uploads.contains_key(id)
+            };
+            if inserted {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        panic!("callback '{}' was never inserted", id);
+    }
+
+    /**
+     * A `ParoApp` whose state are the errors passed to its error handler
+     */
+    pub(crate) fn collecting_errors() -> ParoApp<Vec<ParoError>> {
+        let mut app = ParoApp::new(vec![]);
+        app.set_error_handler(|errors: &mut Vec<ParoError>, error: &ParoError| errors.push(error.clone()));
+        app
+    }
+
+    // the event mode is global, tests that depend on it hold this lock
+    static EVENT_MODE: Mutex<()> = Mutex::new(());
+
+    pub(crate) fn in_event_mode<Output>(mode: EventMode, test: impl FnOnce() -> Output) -> Output {
+        let _lock = EVENT_MODE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        set_event_mode(mode);
+        let output = test();
+        set_event_mode(EventMode::Inline);
+        output
+    }
+
+    /**
+     * The callback ids of the `data-paro-<event>` attributes in delegated mode,
+     * without modifiers
+     */
+    pub(crate) fn delegated_callbacks(html: &str, event: &str) -> Vec<String> {
+        let attribute = format!("data-paro-{}=\"", event);
+        html.match_indices(&attribute)
+            .map(|(start, _)| {
+                let value = &html[start + attribute.len()..];
+                let end = value.find(['"', ':']).unwrap();
+                value[..end].to_owned()
+            })
+            .collect()
+    }
+
+    fn render_counter(paro_app: &mut Arc<RwLock<ParoApp<u64>>>) -> String {
+        __private::register(paro_app, "navigate".to_owned(), false, |count: &mut u64, _: Option<String>| *count = 0);
+        let results = render_region(paro_app, "results", |paro_app| {
+            let id = format!("result-{}", paro_app.read().unwrap().state);
+            __private::register(paro_app, id.clone(), false, |count: &mut u64, _: Option<String>| *count += 1);
+            id
+        });
+        format!("<nav>navigate</nav><div data-paro-region=\"results\">{}</div>", results)
+    }
+
+    #[test]
+    fn region_renders_keep_the_callbacks_outside_of_the_region() {
+        let mut paro_app = Arc::new(RwLock::new(ParoApp::new(0)));
+        render_update(&mut paro_app, render_counter);
+        wait_for_callback(&paro_app, "navigate");
+        wait_for_callback(&paro_app, "result-0");
+
+        for count in 0..150 {
+            {
+                let mut app = paro_app.write().unwrap();
+                app.call(format!("result-{}__PARO__", count)).unwrap();
+                app.context().mark_dirty("results");
+                app.iterate();
+            }
+            let message = render_update(&mut paro_app, render_counter);
+            assert!(message.contains("\"regions\""), "only the region is rendered");
+            wait_for_callback(&paro_app, &format!("result-{}", count + 1));
+        }
+
+        let mut app = paro_app.write().unwrap();
+        assert_eq!(app.call("navigate__PARO__".to_owned()), Ok(()));
+        assert_eq!(app.state, 0);
+        // the results rendered long ago are gone
+        assert_eq!(app.call("result-0__PARO__".to_owned()), Err(ParoError::CallbackNotFound("result-0".to_owned())));
+    }
+
+    #[test]
+    fn empty_values_are_none() {
+        let mut app = ParoApp::new(vec![]);
+        app.insert_typed("set".to_owned(), |values: &mut Vec<Option<u64>>, value: Option<u64>| values.push(value));
+        for value in ["", "undefined", "null", "5"] {
+            app.call(format!("set__PARO__{}", value)).unwrap();
+        }
+        assert_eq!(app.state, vec![None, None, None, Some(5)]);
+    }
+
+    #[test]
+    fn one_shot_callbacks_are_consumed_once_they_ran() {
+        let mut app = ParoApp::new(0);
+        app.insert_once("save".to_owned(), |saved: &mut u64, value: u64| *saved = value);
+
+        assert!(matches!(app.call("save__PARO__abc".to_owned()), Err(ParoError::InvalidValue { .. })));
+        assert_eq!(app.state, 0);
+        // the invalid value did not burn the callback
+        assert_eq!(app.call("save__PARO__7".to_owned()), Ok(()));
+        assert_eq!(app.state, 7);
+        assert_eq!(app.call("save__PARO__8".to_owned()), Err(ParoError::AlreadyConsumed("save".to_owned())));
+        assert_eq!(app.state, 7);
+    }
+
+    #[test]
+    fn once_modifier_makes_callbacks_one_shot() {
+        let mut app = ParoApp::new(0);
+        app.make_one_shot("add");
+        app.insert_typed("add".to_owned(), |count: &mut u64, _: Option<String>| *count += 1);
+        assert_eq!(app.call("add__PARO__".to_owned()), Ok(()));
+        assert_eq!(app.call("add__PARO__".to_owned()), Err(ParoError::AlreadyConsumed("add".to_owned())));
+        assert_eq!(app.state, 1);
+    }
+
+    #[test]
+    fn invalid_values_go_to_the_error_handler() {
+        let mut app = ParoApp::new(0);
+        app.insert_typed("set".to_owned(), |count: &mut u64, value: u64| *count = value);
+        assert!(matches!(app.call("set__PARO__abc".to_owned()), Err(ParoError::InvalidValue { .. })));
+
+        app.set_error_handler(|count: &mut u64, _error: &ParoError| *count = 99);
+        assert_eq!(app.call("set__PARO__abc".to_owned()), Ok(()));
+        assert_eq!(app.state, 99);
+    }
+
+    #[test]
+    fn renders_what_tasks_changed_after_messages_that_do_not_render() {
+        let mut paro_app = Arc::new(RwLock::new(ParoApp::new(0u64)));
+        {
+            let mut app = paro_app.write().unwrap();
+            let context = app.tasks.context();
+            app.tasks.starter()(async move {
+                context.update(|count: &mut u64, _ctx: &mut ParoContext<u64>| *count += 1).await;
             });
-            javascript_call
+            // like a reply to the query the task waited for
+            app.context().apply(ParoAction::NoRender);
         }
+        let message = render_update(&mut paro_app, |paro_app| format!("count {}", paro_app.read().unwrap().state));
+        assert!(message.contains("count 1"), "{}", message);
+    }
+
+    #[test]
+    fn only_the_current_connection_can_be_ended() {
+        let mut app = ParoApp::new(0u64);
+        let (_old_receiver, old) = app.connect();
+        let (_receiver, current) = app.connect();
+        app.context().toast(Level::Info, "still connected");
+        app.update_toasts();
+        app.disconnect(old);
+        assert!(app.client().is_connected(current));
+        assert!(app.take_toasts_to_render().is_some_and(|toasts| toasts.contains("still connected")));
+        app.disconnect(current);
+        assert!(!app.client().is_connected(current));
+        assert_eq!(app.take_toasts_to_render(), Some("".to_owned()));
+    }
+
+    struct Widgets {
+        palette: CommandPalette<u8>,
+        combobox: Combobox<u8>,
+    }
+
+    fn render_widgets(paro_app: &mut Arc<RwLock<ParoApp<Widgets>>>) -> String {
+        let (palette, combobox) = {
+            let app = paro_app.read().unwrap();
+            (app.state.palette.clone(), app.state.combobox.clone())
+        };
+        let palette = render_component(paro_app, &palette, |widgets: &mut Widgets| &mut widgets.palette, |_: &mut Widgets, _| {});
+        let combobox = render_component(paro_app, &combobox, |widgets: &mut Widgets| &mut widgets.combobox,
+            |widgets: &mut Widgets, message: ComboboxMessage<u8>| {
+                if let ComboboxMessage::Search(_) = message {
+                    widgets.combobox.set_suggestions(vec![Suggestion::new("Anna", 1)]);
+                }
+            });
+        palette + &combobox
+    }
+
+    // ` onclick=` and alike
+    fn inline_handlers(html: &str) -> Vec<&str> {
+        html.match_indices(" on")
+            .map(|(start, _)| &html[start + 1..])
+            .filter_map(|rest| rest.split_once('=').map(|(name, _)| name))
+            .filter(|name| name.len() > 2 && name.chars().all(|character| character.is_ascii_lowercase()))
+            .collect()
+    }
+
+    #[test]
+    fn delegated_rendering_needs_no_inline_scripts_or_styles() {
+        let mut paro_app = Arc::new(RwLock::new(ParoApp::new(Widgets {
+            palette: CommandPalette::new("jump"),
+            combobox: Combobox::new("manager", "Manager"),
+        })));
+        {
+            let mut app = paro_app.write().unwrap();
+            app.state.palette.open();
+            app.state.palette.set_results(vec![PaletteResult::new("Anna", 1).detail("Sales")]);
+            app.context().open_dialog(ConfirmDialog::new("Delete?"), |_: &mut Widgets, _: bool, _: &mut ParoContext<Widgets>| {});
+            app.context().open_dialog(PromptDialog::new("Name?", ""), |_: &mut Widgets, _: Option<String>, _: &mut ParoContext<Widgets>| {});
+            app.context().toast(Level::Warning, "Careful");
+        }
+
+        in_event_mode(EventMode::Delegated, || {
+            let first = render_update(&mut paro_app, render_widgets);
+            // the combobox only shows suggestions once the user typed
+            let search = delegated_callbacks(&render_widgets(&mut paro_app), "input").pop().unwrap();
+            wait_for_callback(&paro_app, &search);
+            paro_app.write().unwrap().call(format!("{}__PARO__an", search)).unwrap();
+            let second = render_update(&mut paro_app, render_widgets);
+            assert!(first.contains("paro-dialog-prompt") && first.contains("paro-toast") && first.contains("paro-palette-result"));
+            assert!(second.contains("paro-combobox-suggestion"));
+
+            for message in [first, second] {
+                assert_eq!(inline_handlers(&message), Vec::<&str>::new());
+                assert!(!message.contains("style="), "{}", message);
+            }
+        });
     }
 }
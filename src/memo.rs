@@ -0,0 +1,161 @@
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+
+use crate::ParoApp;
+
+
+/**
+ * A fragment rendered by `memo` together with the callbacks it contains
+ */
+pub(crate) struct MemoEntry {
+    inputs_hash: u64,
+    html: String,
+    pub(crate) callback_ids: Vec<String>,
+    pub(crate) last_used: u128,
+}
+
+
+thread_local! {
+    // callback ids registered while rendering memoized fragments, one Vec per nesting level
+    static RECORDERS: RefCell<Vec<Vec<String>>> = const { RefCell::new(vec![]) };
+}
+
+/**
+ * Remembers that a callback was created while rendering a memoized fragment
+ */
+pub(crate) fn record_callback(id: &str) {
+    RECORDERS.with(|recorders| {
+        if let Some(recorder) = recorders.borrow_mut().last_mut() {
+            recorder.push(id.to_owned());
+        }
+    });
+}
+
+fn record_callbacks(ids: &[String]) {
+    RECORDERS.with(|recorders| {
+        if let Some(recorder) = recorders.borrow_mut().last_mut() {
+            recorder.extend_from_slice(ids);
+        }
+    });
+}
+
+/**
+ * Renders and returns the ids of the callbacks created while rendering. An
+ * enclosing fragment is told about them as well.
+ */
+pub(crate) fn recording<Output, Render: FnOnce() -> Output>(render: Render) -> (Output, Vec<String>) {
+    RECORDERS.with(|recorders| recorders.borrow_mut().push(vec![]));
+    let output = render();
+    let callback_ids = RECORDERS.with(|recorders| recorders.borrow_mut().pop().unwrap_or_default());
+    record_callbacks(&callback_ids);
+    (output, callback_ids)
+}
+
+
+/**
+ * Renders a fragment only if its inputs changed since it was last rendered
+ * under the same key, otherwise the previously rendered html is reused. The
+ * callbacks created while rendering the fragment are kept alive as long as the
+ * fragment is reused, so the html stays clickable.
+ *
+ * `inputs` has to contain everything the fragment reads from your state. A
+ * version number you increase whenever the data changes works as well. Once
+ * a one-shot callback of the fragment was called, the fragment is rendered
+ * again, even if its inputs did not change.
+ *
+ * ```ignore
+ * let navigation = memo(
+ *     &mut paro_app.clone(),
+ *     "navigation",
+ *     &(state.page.clone(), state.list_state.search_term.clone()),
+ *     |paro_app| render_navigation(paro_app).into_string(),
+ * );
+ * ```
+ */
+pub fn memo<State, Inputs, Render>(paro_app: &mut Arc<RwLock<ParoApp<State>>>, key: &str, inputs: &Inputs, render: Render) -> String
+where
+    State: 'static,
+    Inputs: Hash + ?Sized,
+    Render: FnOnce(&mut Arc<RwLock<ParoApp<State>>>) -> String,
+{
+    let mut hasher = DefaultHasher::new();
+    inputs.hash(&mut hasher);
+    let inputs_hash = hasher.finish();
+
+    {
+        let app = paro_app.read().unwrap();
+        let mut memos = app.memos.lock().unwrap();
+        if let Some(entry) = memos.get_mut(key) {
+            if entry.inputs_hash == inputs_hash {
+                entry.last_used = app.iteration;
+                // an enclosing fragment contains our callbacks as well
+                record_callbacks(&entry.callback_ids);
+                return entry.html.clone();
+            }
+        }
+    }
+
+    let (html, callback_ids) = recording(|| render(paro_app));
+
+    let app = paro_app.read().unwrap();
+    app.memos.lock().unwrap().insert(key.to_owned(), MemoEntry {
+        inputs_hash,
+        html: html.clone(),
+        callback_ids,
+        last_used: app.iteration,
+    });
+    html
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::__private::{register, register_once};
+    use crate::tests::wait_for_callback;
+
+    static RENDERS: AtomicUsize = AtomicUsize::new(0);
+
+    // a button that counts up and a one-shot reset button
+    fn render_buttons(paro_app: &mut Arc<RwLock<ParoApp<u64>>>, version: u64) -> String {
+        memo(paro_app, "buttons", &version, |paro_app| {
+            let render = RENDERS.fetch_add(1, Ordering::SeqCst);
+            let add = format!("add-{}", render);
+            let reset = format!("reset-{}", render);
+            register(paro_app, add.clone(), false, |count: &mut u64, _: Option<String>| *count += 1);
+            register_once(paro_app, reset.clone(), |count: &mut u64, _: Option<String>| *count = 0);
+            format!("{} {}", add, reset)
+        })
+    }
+
+    #[test]
+    fn reuses_fragments_until_inputs_change_or_one_shots_were_called() {
+        let mut paro_app = Arc::new(RwLock::new(ParoApp::new(0)));
+        let first = render_buttons(&mut paro_app, 1);
+        let (add, reset) = first.split_once(' ').unwrap();
+        wait_for_callback(&paro_app, add);
+        wait_for_callback(&paro_app, reset);
+
+        // reused, and its callbacks are kept alive even though they were registered long ago
+        for _ in 0..150 {
+            paro_app.write().unwrap().iterate();
+            assert_eq!(render_buttons(&mut paro_app, 1), first);
+        }
+        paro_app.write().unwrap().call(format!("{}__PARO__", add)).unwrap();
+        assert_eq!(paro_app.read().unwrap().state, 1);
+
+        let changed = render_buttons(&mut paro_app, 2);
+        assert_ne!(changed, first);
+        assert_eq!(render_buttons(&mut paro_app, 2), changed);
+
+        let (_, reset) = changed.split_once(' ').unwrap();
+        wait_for_callback(&paro_app, reset);
+        paro_app.write().unwrap().call(format!("{}__PARO__", reset)).unwrap();
+        assert_eq!(paro_app.read().unwrap().state, 0);
+        assert_ne!(render_buttons(&mut paro_app, 2), changed, "the consumed reset button is rendered again");
+    }
+}
@@ -0,0 +1,296 @@
+use crate::{event_attribute, ComponentScope, Hotkey, ParoCommand, ParoComponent, ParoError};
+use crate::hotkey::check_keys;
+use crate::render::{check_name, escape_html};
+
+
+/**
+ * A result of a `CommandPalette` search. Results with a higher score are
+ * shown first.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteResult<Item> {
+    pub title: String,
+    pub detail: Option<String>,
+    pub score: i64,
+    pub item: Item,
+}
+
+impl <Item> PaletteResult<Item> {
+    pub fn new(title: &str, item: Item) -> PaletteResult<Item> {
+        PaletteResult {
+            title: title.to_owned(),
+            detail: None,
+            score: 0,
+            item,
+        }
+    }
+
+    /// shown next to the title
+    pub fn detail(mut self, detail: &str) -> PaletteResult<Item> {
+        self.detail = Some(detail.to_owned());
+        self
+    }
+
+    pub fn score(mut self, score: i64) -> PaletteResult<Item> {
+        self.score = score;
+        self
+    }
+}
+
+
+/**
+ * What a `CommandPalette` tells its parent
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteMessage<Item> {
+    /// The user typed, answer with `CommandPalette::set_results`
+    Search(String),
+    /// The user picked a result, the palette is closed already
+    Chosen(Item),
+}
+
+
+/**
+ * An overlay to jump to anything via the keyboard, opened with Ctrl+K. What
+ * the user types is send to your search debounced, the results you set are
+ * ranked by score and the arrow keys and Enter are handled by paro.js, so
+ * moving through the results needs no round trip.
+ *
+ * ```ignore
+ * (PreEscaped(render_component(
+ *     paro_app,
+ *     &state.palette,
+ *     |state: &mut ApplicationState| &mut state.palette,
+ *     |state: &mut ApplicationState, message: PaletteMessage<Jump>| match message {
+ *         PaletteMessage::Search(query) => {
+ *             let results = state.employees.iter()
+ *                 .filter_map(|employee| fuzzy_score(&query, &employee.login)
+ *                     .map(|score| PaletteResult::new(&employee.login, Jump::Employee(employee.id.clone())).score(score)))
+ *                 .collect();
+ *             state.palette.set_results(results);
+ *         },
+ *         PaletteMessage::Chosen(jump) => state.jump(jump),
+ *     },
+ * )))
+ * ```
+ *
+ * Style it via the `paro-palette-*` classes, the active result has the
+ * `paro-active` class.
+ */
+#[derive(Debug, Clone)]
+pub struct CommandPalette<Item> {
+    name: String,
+    keys: String,
+    placeholder: String,
+    max_results: usize,
+    debounce: u32,
+    open: bool,
+    query: String,
+    results: Vec<PaletteResult<Item>>,
+}
+
+impl <Item> CommandPalette<Item> {
+    /**
+     * `name` tells the palettes on a page apart, it is part of their ids.
+     * Panics unless it only contains ASCII letters, digits, `-` and `_`.
+     */
+    pub fn new(name: &str) -> CommandPalette<Item> {
+        check_name("palette", name);
+        CommandPalette {
+            name: name.to_owned(),
+            keys: "ctrl+k".to_owned(),
+            placeholder: "Search".to_owned(),
+            max_results: 20,
+            debounce: 150,
+            open: false,
+            query: "".to_owned(),
+            results: vec![],
+        }
+    }
+
+    /**
+     * The shortcut that opens the palette, see `Hotkey`. `ParoError::InvalidHotkey`
+     * if `keys` is not a valid shortcut.
+     */
+    pub fn keys(mut self, keys: &str) -> Result<CommandPalette<Item>, ParoError> {
+        check_keys(keys)?;
+        self.keys = keys.to_owned();
+        Ok(self)
+    }
+
+    pub fn placeholder(mut self, placeholder: &str) -> CommandPalette<Item> {
+        self.placeholder = placeholder.to_owned();
+        self
+    }
+
+    /// results with a lower score are dropped, 20 by default
+    pub fn max_results(mut self, max_results: usize) -> CommandPalette<Item> {
+        self.max_results = max_results;
+        self
+    }
+
+    /// milliseconds the user has to stop typing before searching, 150 by default
+    pub fn debounce(mut self, milliseconds: u32) -> CommandPalette<Item> {
+        self.debounce = milliseconds;
+        self
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn results(&self) -> &[PaletteResult<Item>] {
+        &self.results
+    }
+
+    /**
+     * Shows the results, best score first
+     */
+    pub fn set_results(&mut self, mut results: Vec<PaletteResult<Item>>) {
+        results.sort_by_key(|result| std::cmp::Reverse(result.score));
+        results.truncate(self.max_results);
+        self.results = results;
+    }
+
+    fn id(&self, part: &str) -> String {
+        format!("paro-palette-{}-{}", self.name, part)
+    }
+}
+
+impl <Item: Clone + Send + Sync + 'static> ParoComponent for CommandPalette<Item> {
+    type Message = PaletteMessage<Item>;
+
+    fn render<State: Send + Sync + 'static>(&self, scope: &ComponentScope<State, Self>) -> String {
+        let input_id = self.id("input");
+        let focus_input = format!("#{}", input_id);
+        // the keys were checked by `keys`
+        let mut html = scope.hotkey(Hotkey::new(&self.keys, "Open the command palette",
+            move |palette: &mut CommandPalette<Item>, ctx| {
+                palette.open = !palette.open;
+                if palette.open {
+                    ctx.command(ParoCommand::focus(&focus_input));
+                }
+            }));
+        if !self.open {
+            return html;
+        }
+
+        let close = scope.event_with_modifiers("self", |palette: &mut CommandPalette<Item>, _: Option<String>, _| palette.close());
+        let escape = scope.event_with_modifiers("key=Escape,prevent", |palette: &mut CommandPalette<Item>, _: Option<String>, _| palette.close());
+        let search = scope.event_with_modifiers(&format!("debounce={}", self.debounce),
+            |palette: &mut CommandPalette<Item>, query: String, emitter| {
+                palette.query = query.clone();
+                emitter.emit(PaletteMessage::Search(query));
+            });
+        let results_id = self.id("results");
+
+        html.push_str(&format!(
+            r#"<div class="paro-palette-backdrop" {}="{}">"#,
+            event_attribute("click"), escape_html(&close)
+        ));
+        html.push_str(r#"<div class="paro-palette" role="dialog" aria-modal="true" aria-label="Command palette">"#);
+        html.push_str(&format!(
+            r#"<input id="{}" class="paro-palette-input" type="text" role="combobox" autocomplete="off" aria-expanded="true" aria-autocomplete="list" aria-controls="{}" data-paro-listbox="{}" placeholder="{}" value="{}" {}="{}" {}="{}">"#,
+            input_id, results_id, results_id, escape_html(&self.placeholder), escape_html(&self.query),
+            event_attribute("input"), escape_html(&search), event_attribute("keydown"), escape_html(&escape)
+        ));
+        html.push_str(&format!(r#"<ul id="{}" class="paro-palette-results" role="listbox">"#, results_id));
+        for (index, result) in self.results.iter().enumerate() {
+            let item = result.item.clone();
+            let choose = scope.event(move |palette: &mut CommandPalette<Item>, _: Option<String>, emitter| {
+                palette.close();
+                emitter.emit(PaletteMessage::Chosen(item.clone()));
+            });
+            // the first result is chosen with Enter until the user picks another one
+            html.push_str(&format!(
+                r#"<li id="{}" class="paro-palette-result{}" role="option" aria-selected="{}" {}="{}"><span class="paro-palette-title">{}</span>"#,
+                self.id(&format!("result-{}", index)), if index == 0 { " paro-active" } else { "" }, index == 0,
+                event_attribute("click"), escape_html(&choose), escape_html(&result.title)
+            ));
+            if let Some(detail) = &result.detail {
+                html.push_str(&format!(r#" <span class="paro-palette-detail">{}</span>"#, escape_html(detail)));
+            }
+            html.push_str("</li>");
+        }
+        if self.results.is_empty() && !self.query.is_empty() {
+            html.push_str(r#"<li class="paro-palette-empty">No results</li>"#);
+        }
+        html.push_str("</ul></div></div>");
+        html
+    }
+}
+
+
+/**
+ * Scores how well `text` matches what the user typed, `None` if it does not
+ * contain all typed characters in that order. Matches at the start of words
+ * and consecutive matches score higher. Case is ignored.
+ */
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+    for character in query.to_lowercase().chars().filter(|character| !character.is_whitespace()) {
+        let found = position + text[position..].iter().position(|candidate| *candidate == character)?;
+        score += 1;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        if previous_match.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        previous_match = Some(found);
+        position = found + 1;
+    }
+    // shorter texts match better
+    Some(score * 100 - text.len() as i64)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_score_needs_all_characters_in_order() {
+        assert!(fuzzy_score("jdoe", "john.doe").is_some());
+        assert!(fuzzy_score("J DOE", "john.doe").is_some());
+        assert_eq!(fuzzy_score("doej", "john.doe"), None);
+        assert_eq!(fuzzy_score("x", "john.doe"), None);
+    }
+
+    #[test]
+    fn fuzzy_score_prefers_word_starts_runs_and_short_texts() {
+        let score = |query, text| fuzzy_score(query, text).unwrap();
+        assert!(score("doe", "john.doe") > score("oe", "john.doe"));
+        assert!(score("jd", "john.doe") > score("jo", "ajohn.x"));
+        assert!(score("do", "doe") > score("de", "doe"));
+        assert!(score("doe", "doe") > score("doe", "doe.john"));
+        assert_eq!(score("", "doe"), -3);
+    }
+
+    #[test]
+    fn checks_the_keys_before_rendering() {
+        assert!(CommandPalette::<()>::new("jump").keys("ctrl+shift+p").is_ok());
+        assert!(matches!(CommandPalette::<()>::new("jump").keys("ctrl+"), Err(ParoError::InvalidHotkey { .. })));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid palette name")]
+    fn rejects_names_unusable_in_ids() {
+        CommandPalette::<()>::new("jump to");
+    }
+}
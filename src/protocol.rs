@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::collections::HashMap;
+
+use crate::{CanvasFrame, DismissReason, ParoCommand, StorageArea};
+
+
+/**
+ * Messages that are not plain html start with this prefix, followed by json.
+ * Plain html is shown as the whole page, just like before.
+ */
+pub(crate) const MESSAGE_PREFIX: &str = "__PARO__";
+
+
+/**
+ * A message from pâro to paro.js
+ */
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct ServerMessage {
+    // the whole page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+    // region name -> content of the region
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub regions: BTreeMap<String, String>,
+    // all toasts that are shown, send when they changed, see Toast
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toasts: Option<String>,
+    // executed by paro.js after rendering
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<ParoCommand>,
+    // ParoQuery with an additional id paro.js sends back with the reply
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub queries: Vec<Value>,
+    // written to localStorage or sessionStorage, see ParoStorage
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub storage: Vec<StorageWrite>,
+    // handles of images paro.js can forget, see ParoImages
+    #[serde(rename = "releasedImages", skip_serializing_if = "Vec::is_empty")]
+    pub released_images: Vec<String>,
+    // drawn by paro.js without rendering, see ParoCanvas
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub canvas: Vec<CanvasFrame>,
+    // all open dialogs, send when they changed or the page was rendered, see ParoDialog
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dialogs: Option<String>,
+    // ids of files that were received or rejected, see ParoUpload
+    #[serde(rename = "finishedUploads", skip_serializing_if = "Vec::is_empty")]
+    pub finished_uploads: Vec<String>,
+}
+
+
+/**
+ * Sets a key of the storage of the webview or removes it if value is None
+ */
+#[derive(Debug, Serialize)]
+pub(crate) struct StorageWrite {
+    pub area: StorageArea,
+    pub key: String,
+    pub value: Option<String>,
+}
+
+impl ServerMessage {
+    /**
+     * Encodes the message to be send to paro.js. A message with nothing but the
+     * whole page is send as plain html. An empty message tells paro.js that
+     * the event was handled, but nothing needs to be rendered.
+     */
+    pub fn encode(self) -> String {
+        if self.regions.is_empty() && self.toasts.is_none() && self.commands.is_empty()
+            && self.queries.is_empty() && self.storage.is_empty() && self.released_images.is_empty()
+            && self.canvas.is_empty() && self.dialogs.is_none() && self.finished_uploads.is_empty() {
+            if let Some(html) = self.html {
+                return html;
+            }
+        }
+        format!("{}{}", MESSAGE_PREFIX, serde_json::to_string(&self).expect("[paro] could not serialize message"))
+    }
+}
+
+
+/**
+ * A message from paro.js to pâro that is not an event. Send as the prefix
+ * followed by json, so it looks like an event with an empty id to `ParoApp::call`.
+ */
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ClientMessage {
+    // the answer to a query, either value or error is set
+    Reply {
+        id: u64,
+        #[serde(default)]
+        value: Value,
+        error: Option<String>,
+    },
+    // a file of an upload starts, see ParoUpload. upload is the id paro.js gave this file
+    UploadStart {
+        callback: String,
+        upload: String,
+        name: String,
+        mime: String,
+        size: u64,
+    },
+    // the next part of the file, base64 encoded
+    UploadChunk {
+        upload: String,
+        data: String,
+    },
+    UploadEnd {
+        upload: String,
+    },
+    // send when paro.js connects, with the values of ParoStorage
+    Hello {
+        #[serde(default)]
+        local: HashMap<String, String>,
+        #[serde(default)]
+        session: HashMap<String, String>,
+    },
+    // the user pressed Escape or clicked the backdrop of the dialog
+    DismissDialog {
+        dialog: String,
+    },
+    // paro.js removed the toast
+    DismissToast {
+        toast: String,
+        reason: DismissReason,
+    },
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+
+
+/**
+ * A question paro.js answers about the client, asked via `ParoClient::query`.
+ * The query is serialized to json and paro.js answers it with the function in
+ * `window.__PARO__.queries` named like the `query` field, so you can add your
+ * own queries:
+ *
+ * ```ignore
+ * #[derive(Serialize)]
+ * #[serde(tag = "query", rename = "devicePixelRatio")]
+ * pub struct DevicePixelRatio {}
+ *
+ * impl ParoQuery for DevicePixelRatio {
+ *     type Reply = f64;
+ * }
+ *
+ * // in javascript
+ * window.__PARO__.queries.devicePixelRatio = (query) => window.devicePixelRatio;
+ * ```
+ */
+pub trait ParoQuery: Serialize {
+    /// What paro.js answers, parsed from json
+    type Reply: DeserializeOwned;
+}
+
+
+/**
+ * Position and size of an element relative to the viewport, see
+ * `Element.getBoundingClientRect()`
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/**
+ * Asks for the position and size of the element matching the css selector.
+ * `None` if there is no such element.
+ */
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "query", rename = "elementSize")]
+pub struct ElementSize {
+    pub selector: String,
+}
+
+impl ElementSize {
+    pub fn new(selector: &str) -> ElementSize {
+        ElementSize { selector: selector.to_owned() }
+    }
+}
+
+impl ParoQuery for ElementSize {
+    type Reply = Option<Rect>;
+}
+
+
+/**
+ * Asks for the text the user selected, inside an input or textarea as well
+ */
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(tag = "query", rename = "textSelection")]
+pub struct TextSelection {}
+
+impl TextSelection {
+    pub fn new() -> TextSelection {
+        TextSelection {}
+    }
+}
+
+impl ParoQuery for TextSelection {
+    type Reply = String;
+}
+
+
+/**
+ * How far the page or an element is scrolled, in pixels
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ScrollPosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+/**
+ * Asks how far the element matching the css selector is scrolled, or the
+ * page if there is no selector
+ */
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(tag = "query", rename = "scrollOffset")]
+pub struct ScrollOffset {
+    pub selector: Option<String>,
+}
+
+impl ScrollOffset {
+    pub fn page() -> ScrollOffset {
+        ScrollOffset { selector: None }
+    }
+
+    pub fn of(selector: &str) -> ScrollOffset {
+        ScrollOffset { selector: Some(selector.to_owned()) }
+    }
+}
+
+impl ParoQuery for ScrollOffset {
+    type Reply = ScrollPosition;
+}
+
+
+/**
+ * Asks for a value in the `localStorage` of the webview. `None` if it is not set.
+ */
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "query", rename = "localStorage")]
+pub struct LocalStorageItem {
+    pub key: String,
+}
+
+impl LocalStorageItem {
+    pub fn new(key: &str) -> LocalStorageItem {
+        LocalStorageItem { key: key.to_owned() }
+    }
+}
+
+impl ParoQuery for LocalStorageItem {
+    type Reply = Option<String>;
+}
@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use crate::{ParoApp, RegionRenderer};
+use crate::dialog::{render_dialogs, RenderedDialog};
+use crate::memo::recording;
+use crate::protocol::ServerMessage;
+
+
+/**
+ * The callbacks on the page as paro.js shows it. Rendering a region only
+ * replaces the callbacks of that region, so `ParoApp::iterate` keeps the
+ * others alive instead of dropping them after 100 region updates.
+ */
+#[derive(Default)]
+pub(crate) struct RenderedCallbacks {
+    ids: HashSet<String>,
+    // the callbacks of each region and of the dialogs, to replace them once those are rendered again
+    parts: HashMap<RenderedPart, Vec<String>>,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum RenderedPart {
+    Region(String),
+    Dialogs,
+}
+
+impl RenderedCallbacks {
+    pub(crate) fn ids(&self) -> &HashSet<String> {
+        &self.ids
+    }
+
+    fn replace_page(&mut self, ids: Vec<String>) {
+        self.ids = ids.into_iter().collect();
+    }
+
+    // regions rendered inside of the part are part of it, so they are replaced as well
+    fn replace_part(&mut self, part: RenderedPart, ids: Vec<String>) {
+        for id in self.parts.remove(&part).unwrap_or_default() {
+            self.ids.remove(&id);
+        }
+        self.ids.extend(ids.iter().cloned());
+        self.parts.insert(part, ids);
+    }
+}
+
+// renders the region and remembers its callbacks
+fn render_recorded<State: 'static>(paro_app: &mut Arc<RwLock<ParoApp<State>>>, name: &str, render: &RegionRenderer<State>) -> String {
+    let (html, ids) = recording(|| render(paro_app));
+    paro_app.read().unwrap().rendered.lock().unwrap()
+        .replace_part(RenderedPart::Region(name.to_owned()), ids);
+    html
+}
+
+// renders the dialogs and remembers their callbacks
+fn render_dialogs_recorded<State: 'static>(paro_app: &mut Arc<RwLock<ParoApp<State>>>, dialogs: Vec<RenderedDialog<State>>) -> String {
+    let (html, ids) = recording(|| render_dialogs(paro_app, dialogs));
+    paro_app.read().unwrap().rendered.lock().unwrap()
+        .replace_part(RenderedPart::Dialogs, ids);
+    html
+}
+
+
+/**
+ * Renders the content of a named region and remembers how to render it, so
+ * callbacks can re-render just this region via `ParoContext::mark_dirty`
+ * instead of the whole page. Put the returned html into an element with a
+ * `data-paro-region` attribute of the same name:
+ *
+ * ```ignore
+ * html! {
+ *     div data-paro-region="results" {
+ *         (PreEscaped(render_region(&mut paro_app.clone(), "results", render_results)))
+ *     }
+ * }
+ * ```
+ */
+pub fn render_region<State, Render>(paro_app: &mut Arc<RwLock<ParoApp<State>>>, name: &str, render: Render) -> String
+where
+    State: 'static,
+    Render: Fn(&mut Arc<RwLock<ParoApp<State>>>) -> String + Send + Sync + 'static,
+{
+    let render: RegionRenderer<State> = Arc::new(render);
+    paro_app.read().unwrap().regions.lock().unwrap()
+        .insert(name.to_owned(), render.clone());
+    render_recorded(paro_app, name, &render)
+}
+
+
+/**
+ * Renders whatever the callbacks called since the last render asked for and
+ * returns the message to send to paro.js. If callbacks marked regions dirty,
+ * only those regions are rendered. Otherwise the whole page is rendered via
+ * `render_page`. If a callback returned `ParoAction::NoRender`, nothing is
+ * rendered, but the message still has to be send, so paro.js knows the event
+ * was handled. Open dialogs are rendered along with the page, see `ParoDialog`.
+ * Render the first page via `render_update` as well, so pâro knows which
+ * callbacks stay on the page while only regions are rendered again.
+ */
+pub fn render_update<State, Render>(paro_app: &mut Arc<RwLock<ParoApp<State>>>, render_page: Render) -> String
+where
+    State: 'static,
+    Render: FnOnce(&mut Arc<RwLock<ParoApp<State>>>) -> String,
+{
+    let mut message = ServerMessage::default();
+    let skip_render;
+    let dialogs;
+    let renderers: Option<Vec<(String, RegionRenderer<State>)>> = {
+        let mut app = paro_app.write().unwrap();
+        // closing dialogs and async callbacks change the state, and may ask for toasts and such
+        let tasks_ran = app.run_tasks();
+        app.update_toasts();
+        app.handle_reported_errors();
+        message.toasts = app.take_toasts_to_render();
+        message.commands = app.context.take_commands();
+        message.finished_uploads = std::mem::take(&mut app.finished_uploads);
+        // what the tasks changed is rendered, even if the message that woke them up, like a reply to a query, was not
+        skip_render = app.context.take_skip_render() && !tasks_ran;
+        dialogs = app.take_dialogs_to_render(skip_render);
+        let dirty_regions = app.context.take_dirty_regions();
+        let regions = app.regions.lock().unwrap();
+        // a region that is not part of the current page needs the whole page to be rendered
+        dirty_regions.into_iter()
+            .map(|name| regions.get(&name).map(|render| (name, render.clone())))
+            .collect()
+    };
+
+    if skip_render {
+        message.dialogs = dialogs.map(|dialogs| render_dialogs_recorded(paro_app, dialogs));
+        return message.encode();
+    }
+    match renderers {
+        Some(renderers) if !renderers.is_empty() => {
+            for (name, render) in renderers {
+                let html = render_recorded(paro_app, &name, &render);
+                message.regions.insert(name, html);
+            }
+        },
+        _ => {
+            // the page registers the regions it contains again
+            {
+                let app = paro_app.read().unwrap();
+                app.regions.lock().unwrap().clear();
+                app.rendered.lock().unwrap().parts.clear();
+            }
+            let (html, ids) = recording(|| render_page(paro_app));
+            paro_app.read().unwrap().rendered.lock().unwrap().replace_page(ids);
+            message.html = Some(html);
+        },
+    }
+    // after the page, dialogs may use the regions it registered
+    message.dialogs = dialogs.map(|dialogs| render_dialogs_recorded(paro_app, dialogs));
+    message.encode()
+}
+
+
+/**
+ * Panics unless `name` only contains ASCII letters, digits, `-` and `_`, so
+ * components can use it in ids, ARIA references and css selectors as it is
+ */
+pub(crate) fn check_name(component: &str, name: &str) {
+    let valid = !name.is_empty() && name.chars().all(|character| character.is_ascii_alphanumeric() || character == '-' || character == '_');
+    if !valid {
+        panic!("[paro] invalid {} name '{}', only letters, digits, '-' and '_' are allowed", component, name);
+    }
+}
+
+
+/**
+ * Escapes text for html content and attribute values
+ */
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::{ParoClient, ParoError, ParoMessage};
+use crate::protocol::{ServerMessage, StorageWrite};
+
+
+/**
+ * The storage of the webview a `ParoStorage` reads and writes
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageArea {
+    /// `localStorage`, survives restarts
+    Local,
+    /// `sessionStorage`, cleared when the webview is closed
+    Session,
+}
+
+
+/**
+ * A typed key/value store backed by the `localStorage` or `sessionStorage` of
+ * the webview, for small things like ui preferences that should survive a
+ * restart. paro.js sends the stored values when it connects, so reading is
+ * synchronous. Writes are send to paro.js right away. Values are stored as json.
+ *
+ * ```ignore
+ * // in a callback
+ * event!(paro_app, |state, collapsed: bool, ctx| ctx.storage().set("sidebar-collapsed", &collapsed))
+ *
+ * // while rendering
+ * let collapsed = paro_app.read().unwrap().storage().get::<bool>("sidebar-collapsed").unwrap_or(false);
+ * ```
+ *
+ * paro.js only reads and writes keys starting with `window.__PARO__.storagePrefix`,
+ * which is `paro:` by default, so pâro does not see what other scripts store.
+ */
+#[derive(Clone)]
+pub struct ParoStorage {
+    area: StorageArea,
+    values: Arc<Mutex<HashMap<String, String>>>,
+    client: ParoClient,
+}
+
+impl fmt::Debug for ParoStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParoStorage")
+            .field("area", &self.area)
+            .field("values", &self.values.lock().unwrap())
+            .finish()
+    }
+}
+
+impl ParoStorage {
+    pub(crate) fn new(area: StorageArea, client: ParoClient) -> ParoStorage {
+        ParoStorage {
+            area,
+            values: Arc::new(Mutex::new(HashMap::new())),
+            client,
+        }
+    }
+
+    /**
+     * The value stored under the key, `None` if there is none or it is not
+     * of the requested type. The latter is reported to the error handler as
+     * `ParoError::InvalidStoredValue`.
+     */
+    pub fn get<Value: DeserializeOwned>(&self, key: &str) -> Option<Value> {
+        let values = self.values.lock().unwrap();
+        let json = values.get(key)?;
+        match serde_json::from_str(json) {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.client.report(ParoError::InvalidStoredValue {
+                    key: key.to_owned(),
+                    message: error.to_string(),
+                });
+                None
+            },
+        }
+    }
+
+    /**
+     * Stores the value under the key, here and in the webview
+     */
+    pub fn set<Value: Serialize + ?Sized>(&self, key: &str, value: &Value) {
+        let json = serde_json::to_string(value).expect("[paro] could not serialize value to store");
+        self.values.lock().unwrap().insert(key.to_owned(), json.clone());
+        self.write(key, Some(json));
+    }
+
+    /**
+     * Removes the key, here and in the webview
+     */
+    pub fn remove(&self, key: &str) {
+        self.values.lock().unwrap().remove(key);
+        self.write(key, None);
+    }
+
+    /**
+     * All stored keys
+     */
+    pub fn keys(&self) -> Vec<String> {
+        self.values.lock().unwrap().keys().cloned().collect()
+    }
+
+    /**
+     * Replaces all values with what paro.js send when it connected
+     */
+    pub(crate) fn load(&self, values: HashMap<String, String>) {
+        *self.values.lock().unwrap() = values;
+    }
+
+    fn write(&self, key: &str, value: Option<String>) {
+        let message = ServerMessage {
+            storage: vec![StorageWrite {
+                area: self.area,
+                key: key.to_owned(),
+                value,
+            }],
+            ..ServerMessage::default()
+        };
+        if !self.client.send(ParoMessage::Text(message.encode())) {
+            self.client.report(ParoError::StorageNotWritten(key.to_owned()));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{ParoApp, ParoError};
+    use crate::tests::collecting_errors;
+
+    #[test]
+    fn returns_values_of_another_type_without_error_handler() {
+        let mut app = ParoApp::new(vec![]);
+        app.insert_typed("noop".to_owned(), |_errors: &mut Vec<ParoError>, _: Option<String>| {});
+        app.storage().load([("collapsed".to_owned(), "\"yes\"".to_owned())].into());
+        assert_eq!(app.storage().get::<String>("collapsed"), Some("yes".to_owned()));
+        assert_eq!(app.storage().get::<bool>("collapsed"), None);
+        assert_eq!(app.storage().get::<bool>("missing"), None);
+
+        // returned by the next call without an error handler
+        assert!(matches!(app.call("noop__PARO__".to_owned()), Err(ParoError::InvalidStoredValue { key, .. }) if key == "collapsed"));
+        assert_eq!(app.call("noop__PARO__".to_owned()), Ok(()));
+    }
+
+    #[test]
+    fn reports_values_of_another_type() {
+        let mut app = collecting_errors();
+        app.insert_typed("noop".to_owned(), |_errors: &mut Vec<ParoError>, _: Option<String>| {});
+        app.storage().load([("collapsed".to_owned(), "\"yes\"".to_owned())].into());
+        assert_eq!(app.storage().get::<u64>("collapsed"), None);
+        assert_eq!(app.call("noop__PARO__".to_owned()), Ok(()));
+        assert!(matches!(&app.state[..], [ParoError::InvalidStoredValue { key, .. }] if key == "collapsed"));
+    }
+
+    #[test]
+    fn reports_writes_without_connection() {
+        let mut app = collecting_errors();
+        app.insert_typed("noop".to_owned(), |_errors: &mut Vec<ParoError>, _: Option<String>| {});
+        app.storage().set("theme", "dark");
+        assert_eq!(app.storage().get::<String>("theme"), Some("dark".to_owned()));
+        app.call("noop__PARO__".to_owned()).unwrap();
+        assert_eq!(app.state, vec![ParoError::StorageNotWritten("theme".to_owned())]);
+
+        let (_receiver, _connection) = app.connect();
+        app.storage().set("theme", "light");
+        app.call("noop__PARO__".to_owned()).unwrap();
+        assert_eq!(app.state.len(), 1);
+    }
+}
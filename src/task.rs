@@ -0,0 +1,416 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+use futures_channel::oneshot;
+
+use crate::{ConfirmDialog, ParoApp, ParoClient, ParoContext, ParoDialog, ParoMessage, PromptDialog};
+
+
+type TaskFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+type Update<State> = Box<dyn FnOnce(&mut State, &mut ParoContext<State>) + Send + 'static>;
+
+
+/**
+ * What async callbacks get instead of the state, see `event_async!`. The
+ * callback runs until it awaits the user, for example an answer to
+ * `confirm`, and continues once the user answered, before the page is
+ * rendered again:
+ *
+ * ```ignore
+ * button onclick=(event_async!(paro_app, move |ctx: ParoAsyncContext<ApplicationState>, _| async move {
+ *     if ctx.confirm(&format!("Delete {}?", login)).await {
+ *         ctx.update(move |state, _ctx| state.delete(&login)).await;
+ *     }
+ * })) { "Delete" }
+ * ```
+ *
+ * The callback may also await anything else, like a timer or a query. Once
+ * that wakes it, pâro asks for a render via `ParoMessage::Render`, which
+ * continues it. If paro.js disconnects while the callback waits, it is
+ * dropped, so the code after the await never runs. Await it within the callback, pâro only runs
+ * the callbacks, it does not know about your async runtime.
+ */
+pub struct ParoAsyncContext<State> {
+    updates: Arc<Mutex<Vec<Update<State>>>>,
+    scheduler: TaskScheduler,
+}
+
+impl <State> Clone for ParoAsyncContext<State> {
+    fn clone(&self) -> Self {
+        ParoAsyncContext { updates: self.updates.clone(), scheduler: self.scheduler.clone() }
+    }
+}
+
+impl <State: Send + Sync + 'static> ParoAsyncContext<State> {
+    /**
+     * Changes the state, just like a callback would. Use the `ParoContext` to
+     * mark regions dirty, queue commands and such.
+     */
+    pub async fn update<Result, Update>(&self, update: Update) -> Result
+    where
+        Result: Send + 'static,
+        Update: FnOnce(&mut State, &mut ParoContext<State>) -> Result + Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.updates.lock().unwrap().push(Box::new(move |state, context| {
+            let _ = sender.send(update(state, context));
+        }));
+        // the context may be used outside of the tasks pâro runs
+        self.scheduler.wake(None);
+        wait_for(receiver).await
+    }
+
+    /**
+     * Opens the dialog and waits until it is closed, see `ParoDialog`
+     */
+    pub async fn dialog<Dialog: ParoDialog<State>>(&self, dialog: Dialog) -> Dialog::Result {
+        let (sender, receiver) = oneshot::channel();
+        self.update(move |_state, context| {
+            context.open_dialog(dialog, move |_state: &mut State, result, _context: &mut ParoContext<State>| {
+                let _ = sender.send(result);
+            });
+        }).await;
+        wait_for(receiver).await
+    }
+
+    /**
+     * Asks the user to confirm something. true if they did.
+     */
+    pub async fn confirm(&self, question: &str) -> bool {
+        self.dialog(ConfirmDialog::new(question)).await
+    }
+
+    /**
+     * Asks the user for a text, starting with `value`. None if they canceled.
+     */
+    pub async fn prompt(&self, question: &str, value: &str) -> Option<String> {
+        self.dialog(PromptDialog::new(question, value)).await
+    }
+}
+
+// a dropped sender means the task is being dropped as well, as paro.js disconnected
+async fn wait_for<Result>(receiver: oneshot::Receiver<Result>) -> Result {
+    match receiver.await {
+        Ok(result) => result,
+        Err(_canceled) => std::future::pending().await,
+    }
+}
+
+
+// how often run_tasks polls a task at most, so tasks that keep waking themselves
+// do not hold the lock on the ParoApp forever
+const MAX_POLLS: usize = 100;
+
+#[derive(Default)]
+struct Wakeups {
+    // the tasks to poll again
+    tasks: Vec<u64>,
+    // run_tasks picks up what is woken while it polls
+    polling: bool,
+    // render_update runs the tasks, so asking once is enough
+    render_requested: bool,
+}
+
+/**
+ * Remembers which tasks need to be polled again. If that happens while pâro
+ * does not poll them, like when a timer fires, it asks the websocket loop
+ * for a render, which polls them.
+ */
+#[derive(Clone)]
+struct TaskScheduler {
+    wakeups: Arc<Mutex<Wakeups>>,
+    client: ParoClient,
+}
+
+impl TaskScheduler {
+    fn wake(&self, task: Option<u64>) {
+        let mut wakeups = self.wakeups.lock().unwrap();
+        wakeups.tasks.extend(task);
+        if !wakeups.polling && !wakeups.render_requested {
+            wakeups.render_requested = self.client.send(ParoMessage::Render);
+        }
+    }
+
+    fn start_polling(&self) {
+        let mut wakeups = self.wakeups.lock().unwrap();
+        wakeups.polling = true;
+        wakeups.render_requested = false;
+    }
+
+    fn take_woken(&self) -> Vec<u64> {
+        let mut woken = std::mem::take(&mut self.wakeups.lock().unwrap().tasks);
+        woken.sort_unstable();
+        woken.dedup();
+        woken
+    }
+
+    // checks for work left under the lock, so nothing woken meanwhile is missed
+    fn stop_polling(&self, work_left: impl FnOnce() -> bool) {
+        let mut wakeups = self.wakeups.lock().unwrap();
+        wakeups.polling = false;
+        if !wakeups.tasks.is_empty() || work_left() {
+            wakeups.render_requested = self.client.send(ParoMessage::Render);
+        }
+    }
+}
+
+struct TaskWaker {
+    id: u64,
+    scheduler: TaskScheduler,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.scheduler.wake(Some(self.id));
+    }
+}
+
+
+/**
+ * Runs the async callbacks of a `ParoApp`. They are polled whenever pâro
+ * handled an event, so they continue before the page is rendered.
+ */
+pub(crate) struct ParoTasks<State> {
+    // started by callbacks, which cannot reach the ParoTasks
+    started: Arc<Mutex<Vec<TaskFuture>>>,
+    updates: Arc<Mutex<Vec<Update<State>>>>,
+    scheduler: TaskScheduler,
+    // behind a mutex, as futures are not Sync
+    running: Mutex<HashMap<u64, TaskFuture>>,
+    next_id: u64,
+}
+
+impl <State: 'static> ParoTasks<State> {
+    pub(crate) fn new(client: ParoClient) -> ParoTasks<State> {
+        ParoTasks {
+            started: Arc::new(Mutex::new(vec![])),
+            updates: Arc::new(Mutex::new(vec![])),
+            scheduler: TaskScheduler { wakeups: Arc::new(Mutex::new(Wakeups::default())), client },
+            running: Mutex::new(HashMap::new()),
+            next_id: 0,
+        }
+    }
+
+    /**
+     * Returns a function that starts the future the async callback returns
+     */
+    pub(crate) fn starter<Task>(&self) -> impl Fn(Task) + Send + Sync + 'static
+    where
+        Task: Future<Output = ()> + Send + 'static,
+    {
+        let started = self.started.clone();
+        move |task| started.lock().unwrap().push(Box::pin(task))
+    }
+
+    pub(crate) fn context(&self) -> ParoAsyncContext<State> {
+        ParoAsyncContext { updates: self.updates.clone(), scheduler: self.scheduler.clone() }
+    }
+
+    /**
+     * Drops all tasks, so they do not continue
+     */
+    pub(crate) fn cancel(&mut self) {
+        self.started.lock().unwrap().clear();
+        self.updates.lock().unwrap().clear();
+        *self.scheduler.wakeups.lock().unwrap() = Wakeups::default();
+        self.running.get_mut().unwrap().clear();
+    }
+}
+
+
+impl <State: 'static> ParoApp<State> {
+    /**
+     * Polls the async callbacks until all of them wait for something that did
+     * not happen yet, like the user answering a dialog, or were polled
+     * `MAX_POLLS` times. true if any of them ran, so the state may have changed.
+     */
+    pub(crate) fn run_tasks(&mut self) -> bool {
+        let scheduler = self.tasks.scheduler.clone();
+        scheduler.start_polling();
+        let mut ran = false;
+        for _ in 0..MAX_POLLS {
+            self.update_dialogs();
+            let mut progressed = false;
+
+            let started = std::mem::take(&mut *self.tasks.started.lock().unwrap());
+            let mut woken = vec![];
+            for task in started {
+                let id = self.tasks.next_id;
+                self.tasks.next_id += 1;
+                self.tasks.running.get_mut().unwrap().insert(id, task);
+                woken.push(id);
+            }
+
+            woken.extend(scheduler.take_woken());
+            for id in woken {
+                let running = self.tasks.running.get_mut().unwrap();
+                if let Some(task) = running.get_mut(&id) {
+                    progressed = true;
+                    let waker = Waker::from(Arc::new(TaskWaker { id, scheduler: scheduler.clone() }));
+                    if let Poll::Ready(()) = task.as_mut().poll(&mut Context::from_waker(&waker)) {
+                        running.remove(&id);
+                    }
+                }
+            }
+
+            let updates = std::mem::take(&mut *self.tasks.updates.lock().unwrap());
+            for update in updates {
+                progressed = true;
+                update(&mut self.state, &mut self.context);
+            }
+
+            if !progressed {
+                break;
+            }
+            ran = true;
+        }
+        // dialogs opened by the last updates are shown with this render
+        self.update_dialogs();
+        scheduler.stop_polling(|| !self.tasks.updates.lock().unwrap().is_empty());
+        ran
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::RwLock;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{render_update, EventMode};
+    use crate::protocol::MESSAGE_PREFIX;
+    use crate::tests::{delegated_callbacks, in_event_mode, wait_for_callback};
+
+    type Log = Arc<RwLock<ParoApp<Vec<String>>>>;
+
+    fn start<Task>(paro_app: &Log, task: impl FnOnce(ParoAsyncContext<Vec<String>>) -> Task)
+    where
+        Task: Future<Output = ()> + Send + 'static,
+    {
+        let app = paro_app.read().unwrap();
+        app.tasks.starter()(task(app.tasks.context()));
+    }
+
+    fn render(paro_app: &mut Log) -> String {
+        render_update(paro_app, |paro_app| paro_app.read().unwrap().state.join(","))
+    }
+
+    // the open dialogs of a message of render_update
+    fn dialogs(message: &str) -> String {
+        let message: serde_json::Value = serde_json::from_str(message.trim_start_matches(MESSAGE_PREFIX)).unwrap();
+        message["dialogs"].as_str().unwrap().to_owned()
+    }
+
+    fn call(paro_app: &Log, id: &str, value: &str) {
+        wait_for_callback(paro_app, id);
+        paro_app.write().unwrap().call(format!("{}__PARO__{}", id, value)).unwrap();
+    }
+
+    // pending once, after waking itself
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            context.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn confirm_and_prompt_continue_with_the_answer() {
+        let mut paro_app = Arc::new(RwLock::new(ParoApp::new(vec![])));
+        start(&paro_app, |context| async move {
+            let confirmed = context.confirm("Rename?").await;
+            let name = context.prompt("Name?", "Anna").await;
+            context.update(move |log: &mut Vec<String>, _| log.push(format!("{} {:?}", confirmed, name))).await;
+        });
+
+        in_event_mode(EventMode::Delegated, || {
+            // cancel and confirm
+            let buttons = delegated_callbacks(&dialogs(&render(&mut paro_app)), "click");
+            call(&paro_app, &buttons[1], "");
+
+            let prompt = dialogs(&render(&mut paro_app));
+            call(&paro_app, &delegated_callbacks(&prompt, "input")[0], "Bert");
+            call(&paro_app, &delegated_callbacks(&prompt, "submit")[0], "");
+            let message = render(&mut paro_app);
+            assert_eq!(paro_app.read().unwrap().state, vec!["true Some(\"Bert\")"]);
+            assert_eq!(dialogs(&message), "");
+        });
+    }
+
+    #[test]
+    fn dismissed_and_canceled_dialogs_continue_as_well() {
+        let mut paro_app = Arc::new(RwLock::new(ParoApp::new(vec![])));
+        start(&paro_app, |context| async move {
+            let confirmed = context.confirm("Rename?").await;
+            let name = context.prompt("Name?", "Anna").await;
+            context.update(move |log: &mut Vec<String>, _| log.push(format!("{} {:?}", confirmed, name))).await;
+        });
+
+        in_event_mode(EventMode::Delegated, || {
+            // Escape or a click on the backdrop
+            let confirm = delegated_callbacks(&dialogs(&render(&mut paro_app)), "dialog").pop().unwrap();
+            paro_app.write().unwrap().call(format!("{}{{\"dismissDialog\":{{\"dialog\":\"{}\"}}}}", MESSAGE_PREFIX, confirm)).unwrap();
+
+            let cancel = delegated_callbacks(&dialogs(&render(&mut paro_app)), "click").pop().unwrap();
+            call(&paro_app, &cancel, "");
+            render(&mut paro_app);
+            assert_eq!(paro_app.read().unwrap().state, vec!["false None"]);
+        });
+    }
+
+    #[test]
+    fn tasks_woken_from_outside_ask_for_a_render() {
+        let mut paro_app = Arc::new(RwLock::new(ParoApp::new(vec![])));
+        let (mut outgoing, _connection) = paro_app.write().unwrap().connect();
+        let (sender, receiver) = oneshot::channel::<String>();
+        start(&paro_app, |context| async move {
+            let answer = receiver.await.unwrap();
+            context.update(move |log: &mut Vec<String>, _| log.push(answer)).await;
+        });
+        render(&mut paro_app);
+        assert!(outgoing.try_recv().is_err());
+
+        // like a timer firing on another thread
+        std::thread::spawn(move || sender.send("late".to_owned()).unwrap()).join().unwrap();
+        assert_eq!(outgoing.try_recv(), Ok(ParoMessage::Render));
+        let message = render(&mut paro_app);
+        assert!(message.contains("late"), "{}", message);
+        assert_eq!(paro_app.read().unwrap().state, vec!["late"]);
+        assert!(outgoing.try_recv().is_err());
+    }
+
+    #[test]
+    fn tasks_that_keep_waking_themselves_do_not_block_rendering() {
+        let paro_app = Arc::new(RwLock::new(ParoApp::new(vec![])));
+        let (mut outgoing, _connection) = paro_app.write().unwrap().connect();
+        let polls = Arc::new(AtomicUsize::new(0));
+        let counted = polls.clone();
+        start(&paro_app, |_context| async move {
+            loop {
+                counted.fetch_add(1, Ordering::SeqCst);
+                YieldNow(false).await;
+            }
+        });
+
+        assert!(paro_app.write().unwrap().run_tasks());
+        assert_eq!(polls.load(Ordering::SeqCst), MAX_POLLS);
+        // it continues with the next render
+        assert_eq!(outgoing.try_recv(), Ok(ParoMessage::Render));
+        assert!(outgoing.try_recv().is_err());
+        paro_app.write().unwrap().run_tasks();
+        assert_eq!(polls.load(Ordering::SeqCst), 2 * MAX_POLLS);
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{IntoParoAction, ParoAction, ParoApp, ParoContext};
+use crate::render::escape_html;
+
+
+/**
+ * How important a toast is. paro.css styles toasts by level and screen
+ * readers read errors right away.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Level {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Success => "success",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+}
+
+
+/**
+ * Why a toast went away, passed to `Toast::on_dismiss`
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DismissReason {
+    /// It was shown as long as its duration
+    Timeout,
+    /// The user closed it
+    Closed,
+    /// A callback dismissed it via `ParoContext::dismiss_toast`
+    Dismissed,
+}
+
+
+/**
+ * Identifies a toast, to dismiss it via `ParoContext::dismiss_toast`
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ToastHandle(String);
+
+impl ToastHandle {
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+}
+
+
+type DismissCallback<State> = Box<dyn FnOnce(&mut State, DismissReason, &mut ParoContext<State>) + Send + Sync + 'static>;
+
+/**
+ * A short message shown above the page, outside of what you render. Show it
+ * via `ParoContext::show_toast`, or `ParoContext::toast` if the defaults
+ * will do:
+ *
+ * ```ignore
+ * ctx.show_toast(Toast::new(Level::Warning, "Employee deleted")
+ *     .duration(Duration::from_secs(10))
+ *     .on_dismiss(|state: &mut ApplicationState, reason: DismissReason, _ctx: &mut ParoContext<ApplicationState>| {
+ *         state.forget_deleted();
+ *     }));
+ * ```
+ *
+ * Only a few toasts are shown at once, the others wait until those are gone.
+ * See `ParoApp::set_max_toasts`. Style them via the `paro-toasts`,
+ * `paro-toast` and `paro-toast-<level>` classes.
+ */
+pub struct Toast<State> {
+    id: String,
+    level: Level,
+    message: String,
+    // None for the PARO.toastDuration of paro.js
+    duration: Option<Duration>,
+    sticky: bool,
+    on_dismiss: Option<DismissCallback<State>>,
+}
+
+impl <State> fmt::Debug for Toast<State> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Toast")
+            .field("id", &self.id)
+            .field("level", &self.level)
+            .field("message", &self.message)
+            .field("duration", &self.duration)
+            .field("sticky", &self.sticky)
+            .finish()
+    }
+}
+
+impl <State: 'static> Toast<State> {
+    pub fn new(level: Level, message: &str) -> Toast<State> {
+        Toast {
+            id: Uuid::new_v4().to_string(),
+            level,
+            message: message.to_owned(),
+            duration: None,
+            sticky: false,
+            on_dismiss: None,
+        }
+    }
+
+    /**
+     * How long the toast is shown, `PARO.toastDuration` of paro.js by default
+     */
+    pub fn duration(mut self, duration: Duration) -> Toast<State> {
+        self.duration = Some(duration);
+        self
+    }
+
+    /**
+     * Shows the toast until the user closes it or it is dismissed
+     */
+    pub fn sticky(mut self) -> Toast<State> {
+        self.sticky = true;
+        self
+    }
+
+    /**
+     * Called once the toast is gone, however that happened. Not called for
+     * toasts that are dropped because paro.js disconnected.
+     */
+    pub fn on_dismiss<Action, Callback>(mut self, callback: Callback) -> Toast<State>
+    where
+        Action: IntoParoAction,
+        Callback: FnOnce(&mut State, DismissReason, &mut ParoContext<State>) -> Action + Send + Sync + 'static,
+    {
+        self.on_dismiss = Some(Box::new(move |state, reason, context| callback(state, reason, context).apply(context)));
+        self
+    }
+
+    pub fn handle(&self) -> ToastHandle {
+        ToastHandle(self.id.clone())
+    }
+
+    fn render(&self) -> String {
+        let timing = match (self.sticky, self.duration) {
+            (true, _) => " data-paro-toast-sticky".to_owned(),
+            (false, Some(duration)) => format!(" data-paro-toast-duration=\"{}\"", duration.as_millis()),
+            (false, None) => "".to_owned(),
+        };
+        format!(
+            concat!(
+                r#"<div class="paro-toast paro-toast-{}" role="{}" data-paro-toast="{}"{}>"#,
+                r#"<span class="paro-toast-message">{}</span>"#,
+                r#"<button type="button" class="paro-toast-close" aria-label="Close" data-paro-toast-close>&times;</button>"#,
+                "</div>",
+            ),
+            self.level.as_str(),
+            // errors interrupt the screen reader, everything else waits
+            if self.level == Level::Error { "alert" } else { "status" },
+            self.id,
+            timing,
+            escape_html(&self.message)
+        )
+    }
+}
+
+
+/**
+ * A toast callbacks showed or dismissed, applied to the toasts of the
+ * `ParoApp` before the next render
+ */
+pub(crate) enum ToastChange<State> {
+    Show(Toast<State>),
+    Dismiss(String),
+}
+
+impl <State> fmt::Debug for ToastChange<State> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToastChange::Show(toast) => f.debug_tuple("Show").field(toast).finish(),
+            ToastChange::Dismiss(id) => f.debug_tuple("Dismiss").field(id).finish(),
+        }
+    }
+}
+
+/**
+ * The toasts of a session, those shown and those waiting for them to go away
+ */
+pub(crate) struct ParoToasts<State> {
+    shown: Vec<Toast<State>>,
+    waiting: VecDeque<Toast<State>>,
+    max_shown: usize,
+    // whether toasts were shown or dismissed since the last render
+    changed: bool,
+}
+
+impl <State> ParoToasts<State> {
+    pub(crate) fn new() -> ParoToasts<State> {
+        ParoToasts {
+            shown: vec![],
+            waiting: VecDeque::new(),
+            max_shown: 3,
+            changed: false,
+        }
+    }
+
+    fn remove(&mut self, id: &str) -> Option<Toast<State>> {
+        if let Some(index) = self.shown.iter().position(|toast| toast.id == id) {
+            return Some(self.shown.remove(index));
+        }
+        let index = self.waiting.iter().position(|toast| toast.id == id)?;
+        self.waiting.remove(index)
+    }
+
+    fn show_waiting(&mut self) {
+        while self.shown.len() < self.max_shown {
+            match self.waiting.pop_front() {
+                Some(toast) => {
+                    self.shown.push(toast);
+                    self.changed = true;
+                },
+                None => break,
+            }
+        }
+    }
+
+    /**
+     * Drops all toasts without calling back, as paro.js disconnected
+     */
+    pub(crate) fn clear(&mut self) {
+        if !self.shown.is_empty() || !self.waiting.is_empty() {
+            self.changed = true;
+        }
+        self.shown.clear();
+        self.waiting.clear();
+    }
+}
+
+
+impl <State: 'static> ParoApp<State> {
+    /**
+     * How many toasts are shown at once, 3 by default. Further toasts wait
+     * until one of those is gone.
+     */
+    pub fn set_max_toasts(&mut self, max: usize) {
+        self.toasts.max_shown = max.max(1);
+        self.toasts.show_waiting();
+    }
+
+    /**
+     * Shows and dismisses the toasts callbacks asked for
+     */
+    pub(crate) fn update_toasts(&mut self) {
+        loop {
+            let changes = self.context.take_toasts();
+            if changes.is_empty() {
+                break;
+            }
+            for change in changes {
+                match change {
+                    ToastChange::Show(toast) => self.toasts.waiting.push_back(toast),
+                    ToastChange::Dismiss(id) => {
+                        self.dismiss_toast(&id, DismissReason::Dismissed);
+                    },
+                }
+            }
+        }
+        self.toasts.show_waiting();
+    }
+
+    /**
+     * Removes the toast and calls back whoever showed it. false if it has no
+     * callback.
+     */
+    pub(crate) fn dismiss_toast(&mut self, id: &str, reason: DismissReason) -> bool {
+        let Some(toast) = self.toasts.remove(id) else {
+            return false;
+        };
+        self.toasts.changed = true;
+        match toast.on_dismiss {
+            Some(on_dismiss) => {
+                on_dismiss(&mut self.state, reason, &mut self.context);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /**
+     * paro.js removed the toast, after its time ran out or the user closed it
+     */
+    pub(crate) fn toast_dismissed(&mut self, id: &str, reason: DismissReason) {
+        // nothing to render but the toasts, unless the callback changed something
+        if !self.dismiss_toast(id, reason) {
+            self.context.apply(ParoAction::NoRender);
+        }
+        self.update_toasts();
+    }
+
+    /**
+     * The toasts to send with the next render, `None` if paro.js can keep
+     * the ones it has
+     */
+    pub(crate) fn take_toasts_to_render(&mut self) -> Option<String> {
+        if !std::mem::take(&mut self.toasts.changed) {
+            return None;
+        }
+        Some(self.toasts.shown.iter().map(|toast| toast.render()).collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shown(app: &ParoApp<Vec<String>>) -> Vec<&str> {
+        app.toasts.shown.iter().map(|toast| toast.message.as_str()).collect()
+    }
+
+    fn remember_dismiss(message: &str) -> Toast<Vec<String>> {
+        let toast = Toast::new(Level::Info, message);
+        let message = message.to_owned();
+        toast.on_dismiss(move |dismissed: &mut Vec<String>, reason: DismissReason, _ctx: &mut ParoContext<Vec<String>>| {
+            dismissed.push(format!("{} {:?}", message, reason));
+        })
+    }
+
+    #[test]
+    fn shows_at_most_max_toasts_and_queues_the_rest() {
+        let mut app = ParoApp::new(vec![]);
+        let first = app.context().show_toast(remember_dismiss("first"));
+        for message in ["second", "third", "fourth"] {
+            app.context().toast(Level::Info, message);
+        }
+        app.update_toasts();
+        assert_eq!(shown(&app), vec!["first", "second", "third"]);
+        assert!(app.take_toasts_to_render().is_some());
+        assert_eq!(app.take_toasts_to_render(), None);
+
+        app.toast_dismissed(first.id(), DismissReason::Closed);
+        assert_eq!(app.state, vec!["first Closed"]);
+        assert_eq!(shown(&app), vec!["second", "third", "fourth"]);
+
+        app.set_max_toasts(1);
+        app.context().toast(Level::Info, "fifth");
+        app.update_toasts();
+        assert_eq!(shown(&app), vec!["second", "third", "fourth"]);
+        app.set_max_toasts(4);
+        assert_eq!(shown(&app), vec!["second", "third", "fourth", "fifth"]);
+    }
+
+    #[test]
+    fn dismisses_waiting_toasts_without_showing_them() {
+        let mut app = ParoApp::new(vec![]);
+        app.set_max_toasts(1);
+        app.context().toast(Level::Info, "shown");
+        let waiting = app.context().show_toast(remember_dismiss("waiting"));
+        app.update_toasts();
+        app.context().dismiss_toast(&waiting);
+        app.update_toasts();
+        assert_eq!(app.state, vec!["waiting Dismissed"]);
+        assert_eq!(shown(&app), vec!["shown"]);
+        assert!(app.toasts.waiting.is_empty());
+    }
+}
@@ -0,0 +1,589 @@
+use std::io::{self, Read};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use uuid::Uuid;
+
+use crate::{ParoAction, ParoApp, ParoContext, ParoError};
+use crate::__private::{emit_event_call, insert_later};
+use crate::memo::record_callback;
+
+
+/**
+ * A file the user uploaded, see `ParoUpload::new`
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParoFile {
+    pub name: String,
+    /// as reported by the webview, can be empty
+    pub mime: String,
+    pub size: u64,
+    pub bytes: Vec<u8>,
+}
+
+/**
+ * A file that is being uploaded, see `ParoUpload::streaming`
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileInfo {
+    pub name: String,
+    /// as reported by the webview, can be empty
+    pub mime: String,
+    pub size: u64,
+}
+
+/**
+ * How much of a file was received so far, see `ParoUpload::on_progress`
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadProgress {
+    pub name: String,
+    pub received: u64,
+    pub size: u64,
+}
+
+
+/**
+ * Reads an uploaded file while it is still being received, see
+ * `ParoUpload::streaming`. Reading blocks until the next chunk arrives. If the
+ * upload fails, for example because the connection was lost, reading returns
+ * an error.
+ */
+pub struct UploadReader {
+    chunks: mpsc::Receiver<Result<Vec<u8>, String>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl Read for UploadReader {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        while self.position >= self.chunk.len() {
+            match self.chunks.recv() {
+                Ok(Ok(chunk)) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                },
+                Ok(Err(reason)) => return Err(io::Error::other(reason)),
+                // all chunks received
+                Err(_) => return Ok(0),
+            }
+        }
+        let read = buffer.len().min(self.chunk.len() - self.position);
+        buffer[..read].copy_from_slice(&self.chunk[self.position..self.position + read]);
+        self.position += read;
+        Ok(read)
+    }
+}
+
+
+type FileCallback<State> = Box<dyn FnMut(&mut State, ParoFile, &mut ParoContext<State>) + Send + Sync + 'static>;
+type ReadUpload = Box<dyn FnOnce(UploadReader) + Send + 'static>;
+type StreamCallback<State> = Box<dyn FnMut(&mut State, FileInfo, &mut ParoContext<State>) -> ReadUpload + Send + Sync + 'static>;
+type ProgressCallback<State> = Box<dyn FnMut(&mut State, UploadProgress, &mut ParoContext<State>) + Send + Sync + 'static>;
+
+enum Receive<State> {
+    Bytes(FileCallback<State>),
+    Stream(StreamCallback<State>),
+}
+
+
+/**
+ * Receives files the user selected in an `<input type="file">`. paro.js
+ * streams them in chunks over the paro connection. Register it via `upload`
+ * and put the result into the onchange attribute of the input:
+ *
+ * ```ignore
+ * input type="file" accept=".csv" onchange=(upload(paro_app, ParoUpload::new(
+ *     |state: &mut ApplicationState, file: ParoFile, ctx: &mut ParoContext<ApplicationState>| state.import_csv(&file.bytes),
+ * ).max_size(1024 * 1024))) {}
+ * ```
+ *
+ * Files larger than the maximum size are rejected and reported to your error
+ * handler as `ParoError::UploadRejected`. Multiple files are received one
+ * after another.
+ */
+pub struct ParoUpload<State> {
+    receive: Receive<State>,
+    max_size: u64,
+    accept: Vec<String>,
+    on_progress: Option<ProgressCallback<State>>,
+}
+
+impl <State: 'static> ParoUpload<State> {
+    /// Used if you do not set a maximum size via `max_size`
+    pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+    /**
+     * Calls the callback once the whole file was received
+     */
+    pub fn new<Callback>(callback: Callback) -> ParoUpload<State>
+    where
+        Callback: FnMut(&mut State, ParoFile, &mut ParoContext<State>) + Send + Sync + 'static,
+    {
+        ParoUpload {
+            receive: Receive::Bytes(Box::new(callback)),
+            max_size: ParoUpload::<State>::DEFAULT_MAX_SIZE,
+            accept: vec![],
+            on_progress: None,
+        }
+    }
+
+    /**
+     * For files too large to keep in memory. Calls the callback as soon as the
+     * upload starts, it returns what reads the file while it arrives:
+     *
+     * ```ignore
+     * ParoUpload::streaming(|state: &mut ApplicationState, info: FileInfo, _ctx: &mut ParoContext<ApplicationState>| {
+     *     state.importing = Some(info.name);
+     *     move |mut reader: UploadReader| {
+     *         let mut file = File::create("import.csv").unwrap();
+     *         std::io::copy(&mut reader, &mut file).unwrap();
+     *     }
+     * })
+     * ```
+     *
+     * pâro runs the reading on a thread of its own, as the chunks are only
+     * received while your `ParoApp` is not locked, which it is while the
+     * callback runs.
+     */
+    pub fn streaming<Callback, Reader>(mut callback: Callback) -> ParoUpload<State>
+    where
+        Callback: FnMut(&mut State, FileInfo, &mut ParoContext<State>) -> Reader + Send + Sync + 'static,
+        Reader: FnOnce(UploadReader) + Send + 'static,
+    {
+        ParoUpload {
+            receive: Receive::Stream(Box::new(move |state, info, context| Box::new(callback(state, info, context)))),
+            max_size: ParoUpload::<State>::DEFAULT_MAX_SIZE,
+            accept: vec![],
+            on_progress: None,
+        }
+    }
+
+    /**
+     * Larger files are rejected, paro.js does not even send them
+     */
+    pub fn max_size(mut self, max_size: u64) -> ParoUpload<State> {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Only accepts files matching one of the given types, like the accept
+    /// attribute of an input: a mime type like `text/csv`, all subtypes like
+    /// `image/*` or a file extension like `.csv`. Other files are rejected,
+    /// paro.js does not even send them. Accepts everything by default.
+    pub fn accept(mut self, types: &[&str]) -> ParoUpload<State> {
+        self.accept = types.iter().map(|accepted| accepted.trim().to_lowercase()).collect();
+        self
+    }
+
+    fn accepts(&self, info: &FileInfo) -> bool {
+        let mime = info.mime.to_lowercase();
+        let name = info.name.to_lowercase();
+        self.accept.is_empty() || self.accept.iter().any(|accepted| {
+            if accepted.starts_with('.') {
+                name.ends_with(accepted.as_str())
+            } else if let Some(kind) = accepted.strip_suffix("/*") {
+                mime.split('/').next() == Some(kind)
+            } else {
+                mime == *accepted
+            }
+        })
+    }
+
+    // for paro.js, like `upload,maxSize=1024,accept=text%2Fcsv%2C.csv`
+    fn modifiers(&self) -> String {
+        let mut modifiers = format!("upload,maxSize={}", self.max_size);
+        if !self.accept.is_empty() {
+            let accept: String = self.accept.join(",").bytes()
+                .map(|byte| match byte {
+                    b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'*' | b'+' => (byte as char).to_string(),
+                    _ => format!("%{:02X}", byte),
+                })
+                .collect();
+            modifiers.push_str(&format!(",accept={}", accept));
+        }
+        modifiers
+    }
+
+    /**
+     * Called for every chunk received. Without it nothing is rendered while
+     * the file is received, with it the page is rendered after each chunk,
+     * unless you mark regions dirty or return `ParoAction::NoRender` via `ctx.apply`.
+     */
+    pub fn on_progress<Callback>(mut self, callback: Callback) -> ParoUpload<State>
+    where
+        Callback: FnMut(&mut State, UploadProgress, &mut ParoContext<State>) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+}
+
+
+/**
+ * Registers the upload and returns the javascript to put into the onchange
+ * attribute of an `<input type="file">`, just like event! does.
+ */
+pub fn upload<State>(paro_app: &Arc<RwLock<ParoApp<State>>>, upload: ParoUpload<State>) -> String
+where
+    State: Send + Sync + 'static,
+{
+    let modifiers = upload.modifiers();
+    let id = register_upload(paro_app, upload);
+    emit_event_call(&id, &modifiers)
+}
+
+/**
+ * Receives files dropped from the file manager onto the element and files or
+ * images pasted from the clipboard while the focus is inside the element.
+ * Put the result into its `data-paro-file-drop` attribute. If there is no
+ * such element around the focus, pasted files go to the first one on the page.
+ * While files are dragged over it, the element has the `paro-file-over` class.
+ *
+ * ```ignore
+ * div data-paro-file-drop=(file_drop(paro_app, ParoUpload::new(
+ *     |state: &mut ApplicationState, file: ParoFile, ctx: &mut ParoContext<ApplicationState>| state.attach(file),
+ * ).accept(&["image/png", "image/jpeg"]))) { "drop or paste a screenshot here" }
+ * ```
+ */
+pub fn file_drop<State>(paro_app: &Arc<RwLock<ParoApp<State>>>, upload: ParoUpload<State>) -> String
+where
+    State: Send + Sync + 'static,
+{
+    let modifiers = upload.modifiers();
+    let id = register_upload(paro_app, upload);
+    format!("{}:{}", id, modifiers)
+}
+
+fn register_upload<State>(paro_app: &Arc<RwLock<ParoApp<State>>>, upload: ParoUpload<State>) -> String
+where
+    State: Send + Sync + 'static,
+{
+    let id = Uuid::new_v4().to_string();
+    record_callback(&id);
+    let upload_id = id.clone();
+    insert_later(paro_app, move |data| {
+        let iteration = data.iteration;
+        data.uploads.insert(upload_id, (iteration, Arc::new(Mutex::new(upload))));
+    });
+    id
+}
+
+
+// as stored by ParoApp, shared with the transfers of its files
+pub(crate) type SharedUpload<State> = Arc<Mutex<ParoUpload<State>>>;
+
+
+/**
+ * A file that is being received
+ */
+pub(crate) struct Transfer<State> {
+    upload: SharedUpload<State>,
+    info: FileInfo,
+    received: u64,
+    bytes: Vec<u8>,
+    // only for streaming uploads
+    stream: Option<mpsc::Sender<Result<Vec<u8>, String>>>,
+}
+
+impl <State> Transfer<State> {
+    /**
+     * Lets a streaming reader know that the rest of the file will not arrive
+     */
+    pub(crate) fn abort(self, reason: &str) {
+        if let Some(stream) = self.stream {
+            let _ = stream.send(Err(reason.to_owned()));
+        }
+    }
+}
+
+
+impl <State: 'static> ParoApp<State> {
+    pub(crate) fn start_upload(&mut self, callback: &str, transfer_id: String, info: FileInfo) -> Result<(), ParoError> {
+        // nothing changed yet
+        self.context.apply(ParoAction::NoRender);
+        let upload = match self.uploads.get(callback) {
+            Some((_, upload)) => upload.clone(),
+            None => {
+                self.finished_uploads.push(transfer_id);
+                return self.handle_error(ParoError::CallbackNotFound(callback.to_owned()));
+            },
+        };
+        let max_size = upload.lock().unwrap().max_size;
+        if info.size > max_size {
+            self.finished_uploads.push(transfer_id);
+            return self.handle_error(ParoError::UploadRejected {
+                name: info.name,
+                reason: format!("file has {} bytes, but at most {} are allowed", info.size, max_size),
+            });
+        }
+        if !upload.lock().unwrap().accepts(&info) {
+            self.finished_uploads.push(transfer_id);
+            return self.handle_error(ParoError::UploadRejected {
+                reason: format!("files of type '{}' are not accepted", info.mime),
+                name: info.name,
+            });
+        }
+        let mut transfer = Transfer {
+            upload: upload.clone(),
+            info: info.clone(),
+            received: 0,
+            bytes: vec![],
+            stream: None,
+        };
+        if let Receive::Stream(callback) = &mut upload.lock().unwrap().receive {
+            let (sender, receiver) = mpsc::channel();
+            transfer.stream = Some(sender);
+            let reader = UploadReader { chunks: receiver, chunk: vec![], position: 0 };
+            // the callback decides whether to render
+            self.context.take_skip_render();
+            let read = callback(&mut self.state, info, &mut self.context);
+            std::thread::spawn(move || read(reader));
+        }
+        self.transfers.insert(transfer_id, transfer);
+        Ok(())
+    }
+
+    pub(crate) fn receive_chunk(&mut self, transfer_id: &str, data: &str) -> Result<(), ParoError> {
+        self.context.apply(ParoAction::NoRender);
+        let chunk = match BASE64.decode(data) {
+            Ok(chunk) => chunk,
+            Err(error) => return self.reject_upload(transfer_id, format!("invalid chunk: {}", error)),
+        };
+        let Some(transfer) = self.transfers.get_mut(transfer_id) else {
+            // already rejected or the connection was lost
+            return Ok(());
+        };
+        transfer.received += chunk.len() as u64;
+        let upload = transfer.upload.clone();
+        let max_size = upload.lock().unwrap().max_size;
+        if transfer.received > max_size || transfer.received > transfer.info.size {
+            return self.reject_upload(transfer_id, "received more bytes than announced or allowed".to_owned());
+        }
+        let progress = UploadProgress {
+            name: transfer.info.name.clone(),
+            received: transfer.received,
+            size: transfer.info.size,
+        };
+        match &transfer.stream {
+            Some(stream) => {
+                // the reader was dropped, nobody is interested in the rest
+                let _ = stream.send(Ok(chunk));
+            },
+            None => transfer.bytes.extend_from_slice(&chunk),
+        }
+        let mut upload = upload.lock().unwrap();
+        if let Some(on_progress) = upload.on_progress.as_mut() {
+            self.context.take_skip_render();
+            on_progress(&mut self.state, progress, &mut self.context);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish_upload(&mut self, transfer_id: &str) -> Result<(), ParoError> {
+        let Some(transfer) = self.transfers.remove(transfer_id) else {
+            self.context.apply(ParoAction::NoRender);
+            return Ok(());
+        };
+        self.finished_uploads.push(transfer_id.to_owned());
+        if transfer.received != transfer.info.size {
+            let name = transfer.info.name.clone();
+            transfer.abort("upload ended before the whole file was received");
+            self.context.apply(ParoAction::NoRender);
+            return self.handle_error(ParoError::UploadRejected {
+                name,
+                reason: "upload ended before the whole file was received".to_owned(),
+            });
+        }
+        let mut upload = transfer.upload.lock().unwrap();
+        match &mut upload.receive {
+            Receive::Bytes(callback) => callback(&mut self.state, ParoFile {
+                name: transfer.info.name,
+                mime: transfer.info.mime,
+                size: transfer.info.size,
+                bytes: transfer.bytes,
+            }, &mut self.context),
+            // dropping the sender lets the reader know the file is complete
+            Receive::Stream(_) => self.context.apply(ParoAction::NoRender),
+        }
+        Ok(())
+    }
+
+    /**
+     * Aborts all uploads in progress, as the connection they came from is gone
+     */
+    pub(crate) fn abort_uploads(&mut self) {
+        for (_id, transfer) in self.transfers.drain() {
+            transfer.abort("connection to paro.js was lost");
+        }
+        self.finished_uploads.clear();
+    }
+
+    fn reject_upload(&mut self, transfer_id: &str, reason: String) -> Result<(), ParoError> {
+        let Some(transfer) = self.transfers.remove(transfer_id) else {
+            return Ok(());
+        };
+        self.finished_uploads.push(transfer_id.to_owned());
+        let name = transfer.info.name.clone();
+        transfer.abort(&reason);
+        self.handle_error(ParoError::UploadRejected { name, reason })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::render_update;
+    use crate::tests::{collecting_errors, wait_for_callback};
+
+    fn info(name: &str, mime: &str) -> FileInfo {
+        FileInfo { name: name.to_owned(), mime: mime.to_owned(), size: 1 }
+    }
+
+    #[test]
+    fn accepts_like_the_accept_attribute() {
+        let upload = ParoUpload::new(|_: &mut (), _: ParoFile, _: &mut ParoContext<()>| {});
+        assert!(upload.accepts(&info("anything.bin", "")));
+
+        let upload = upload.accept(&["text/csv", "image/*", " .TXT "]);
+        assert!(upload.accepts(&info("data.csv", "text/csv")));
+        assert!(upload.accepts(&info("data", "TEXT/CSV")));
+        assert!(upload.accepts(&info("photo.png", "image/png")));
+        assert!(upload.accepts(&info("notes.txt", "")));
+        assert!(upload.accepts(&info("NOTES.TXT", "application/octet-stream")));
+        assert!(!upload.accepts(&info("data.json", "application/json")));
+        assert!(!upload.accepts(&info("txt", "")));
+        assert!(!upload.accepts(&info("imagepng", "imagery/png")));
+    }
+
+    #[test]
+    fn encodes_limits_for_paro_js() {
+        let upload = ParoUpload::new(|_: &mut (), _: ParoFile, _: &mut ParoContext<()>| {}).max_size(1024);
+        assert_eq!(upload.modifiers(), "upload,maxSize=1024");
+        let upload = upload.accept(&["text/csv", ".csv"]);
+        assert_eq!(upload.modifiers(), "upload,maxSize=1024,accept=text%2Fcsv%2C.csv");
+    }
+
+    fn message(message: serde_json::Value) -> String {
+        format!("__PARO__{}", message)
+    }
+
+    fn start(upload: &str, name: &str, mime: &str, size: u64) -> String {
+        message(json!({ "uploadStart": { "callback": "upload", "upload": upload, "name": name, "mime": mime, "size": size } }))
+    }
+
+    fn chunk(upload: &str, bytes: &[u8]) -> String {
+        message(json!({ "uploadChunk": { "upload": upload, "data": BASE64.encode(bytes) } }))
+    }
+
+    fn end(upload: &str) -> String {
+        message(json!({ "uploadEnd": { "upload": upload } }))
+    }
+
+    fn app() -> ParoApp<Vec<ParoFile>> {
+        let mut app = ParoApp::new(vec![]);
+        let upload = ParoUpload::new(|files: &mut Vec<ParoFile>, file: ParoFile, _: &mut ParoContext<Vec<ParoFile>>| files.push(file))
+            .max_size(4)
+            .accept(&["text/plain"]);
+        app.uploads.insert("upload".to_owned(), (0, Arc::new(Mutex::new(upload))));
+        app
+    }
+
+    #[test]
+    fn receives_files_in_chunks() {
+        let mut paro_app = Arc::new(RwLock::new(app()));
+        {
+            let mut app = paro_app.write().unwrap();
+            app.call(start("upload-1", "a.txt", "text/plain", 4)).unwrap();
+            app.call(chunk("upload-1", b"ab")).unwrap();
+            app.call(chunk("upload-1", b"cd")).unwrap();
+            assert!(app.state.is_empty());
+        }
+        // nothing to render, but paro.js stops showing the upload as pending once it ended
+        assert_eq!(render_update(&mut paro_app, |_| "page".to_owned()), "__PARO__{}");
+        paro_app.write().unwrap().call(end("upload-1")).unwrap();
+        let message = render_update(&mut paro_app, |_| "page".to_owned());
+        assert!(message.contains(r#""finishedUploads":["upload-1"]"#), "{}", message);
+        assert_eq!(paro_app.read().unwrap().state, vec![ParoFile {
+            name: "a.txt".to_owned(),
+            mime: "text/plain".to_owned(),
+            size: 4,
+            bytes: b"abcd".to_vec(),
+        }]);
+    }
+
+    #[test]
+    fn streams_files_to_a_reader_that_blocks() {
+        let (sender, read) = mpsc::channel();
+        let mut app = ParoApp::new(vec![]);
+        let upload = ParoUpload::streaming(move |names: &mut Vec<String>, info: FileInfo, _: &mut ParoContext<Vec<String>>| {
+            names.push(info.name);
+            let sender = sender.clone();
+            move |mut reader: UploadReader| {
+                let mut bytes = vec![];
+                let _ = sender.send(reader.read_to_end(&mut bytes).map(|_| bytes).map_err(|error| error.to_string()));
+            }
+        });
+        app.uploads.insert("upload".to_owned(), (0, Arc::new(Mutex::new(upload))));
+
+        app.call(start("upload-1", "a.txt", "text/plain", 4)).unwrap();
+        assert_eq!(app.state, vec!["a.txt"]);
+        app.call(chunk("upload-1", b"ab")).unwrap();
+        app.call(chunk("upload-1", b"cd")).unwrap();
+        app.call(end("upload-1")).unwrap();
+        assert_eq!(read.recv_timeout(std::time::Duration::from_secs(5)), Ok(Ok(b"abcd".to_vec())));
+
+        app.call(start("upload-2", "b.txt", "text/plain", 4)).unwrap();
+        app.call(chunk("upload-2", b"ab")).unwrap();
+        app.abort_uploads();
+        assert_eq!(read.recv_timeout(std::time::Duration::from_secs(5)), Ok(Err("connection to paro.js was lost".to_owned())));
+    }
+
+    #[test]
+    fn dropped_files_of_other_types_are_filtered_and_reported() {
+        let paro_app = Arc::new(RwLock::new(collecting_errors()));
+        let received = Arc::new(Mutex::new(vec![]));
+        let files = received.clone();
+        let drop_zone = file_drop(&paro_app, ParoUpload::new(move |_: &mut Vec<ParoError>, file: ParoFile, _: &mut ParoContext<Vec<ParoError>>| {
+            files.lock().unwrap().push(file.name);
+        }).accept(&["image/png", ".jpg"]));
+        // paro.js filters with the same accept list before sending anything
+        let (id, modifiers) = drop_zone.split_once(':').unwrap();
+        assert!(modifiers.ends_with("accept=image%2Fpng%2C.jpg"), "{}", modifiers);
+        wait_for_callback(&paro_app, id);
+
+        let mut app = paro_app.write().unwrap();
+        let start = |upload: &str, name: &str, mime: &str| message(json!({
+            "uploadStart": { "callback": id, "upload": upload, "name": name, "mime": mime, "size": 1 }
+        }));
+        for (upload, name, mime) in [("upload-1", "notes.txt", "text/plain"), ("upload-2", "shot.png", "image/png"), ("upload-3", "photo.jpg", "")] {
+            app.call(start(upload, name, mime)).unwrap();
+            app.call(chunk(upload, b"x")).unwrap();
+            app.call(end(upload)).unwrap();
+        }
+        assert_eq!(*received.lock().unwrap(), vec!["shot.png", "photo.jpg"]);
+        assert_eq!(app.state, vec![ParoError::UploadRejected {
+            name: "notes.txt".to_owned(),
+            reason: "files of type 'text/plain' are not accepted".to_owned(),
+        }]);
+    }
+
+    #[test]
+    fn rejects_files_too_large_or_of_another_type() {
+        let mut app = app();
+        assert!(matches!(app.call(start("upload-1", "a.txt", "text/plain", 5)), Err(ParoError::UploadRejected { .. })));
+        assert!(matches!(app.call(start("upload-2", "a.png", "image/png", 1)), Err(ParoError::UploadRejected { .. })));
+        // more than announced
+        app.call(start("upload-3", "a.txt", "text/plain", 1)).unwrap();
+        assert!(matches!(app.call(chunk("upload-3", b"ab")), Err(ParoError::UploadRejected { .. })));
+        // ignored once rejected
+        assert_eq!(app.call(chunk("upload-3", b"c")), Ok(()));
+        assert_eq!(app.call(end("upload-3")), Ok(()));
+        assert!(app.state.is_empty());
+        assert_eq!(app.finished_uploads, vec!["upload-1", "upload-2", "upload-3"]);
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+
+
+/**
+ * Converts the raw value send by paro.js (the value of the input field,
+ * textarea, checkbox, etc. that triggered the event) into the type your
+ * callback expects.
+ *
+ * Implemented for `String`, `Option<T>`, `bool`, `char` and all number types.
+ * Wrap your type in `Parsed<T>` to use its `FromStr` implementation or in
+ * `Json<T>` to deserialize it with serde. You can implement it for your own
+ * types as well.
+ */
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as value of a pâro callback",
+    note = "wrap it in `Parsed<{Self}>` if it implements FromStr, in `Json<{Self}>` if it implements serde::Deserialize or implement `FromParoValue` for it"
+)]
+pub trait FromParoValue: Sized {
+    fn from_paro_value(value: Option<String>) -> Result<Self, String>;
+}
+
+/// An empty field is an empty String, not an error.
+impl FromParoValue for String {
+    fn from_paro_value(value: Option<String>) -> Result<Self, String> {
+        Ok(value.unwrap_or_default())
+    }
+}
+
+/// `None` if the client did not send a value, otherwise the parsed value.
+impl<T: FromParoValue> FromParoValue for Option<T> {
+    fn from_paro_value(value: Option<String>) -> Result<Self, String> {
+        match value {
+            None => Ok(None),
+            Some(value) => T::from_paro_value(Some(value)).map(Some),
+        }
+    }
+}
+
+fn parse<T>(value: Option<String>) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match value {
+        None => Err("no value".to_owned()),
+        Some(value) => value.trim().parse::<T>()
+            .map_err(|error| format!("could not parse '{}': {}", value, error)),
+    }
+}
+
+macro_rules! from_str_paro_value {
+    ($($type:ty),*) => {
+        $(
+            impl FromParoValue for $type {
+                fn from_paro_value(value: Option<String>) -> Result<Self, String> {
+                    parse::<$type>(value)
+                }
+            }
+        )*
+    }
+}
+
+from_str_paro_value!(
+    bool, char,
+    u8, u16, u32, u64, u128, usize,
+    i8, i16, i32, i64, i128, isize,
+    f32, f64
+);
+
+
+/**
+ * Parses the value with the `FromStr` implementation of `T`.
+ *
+ * ```ignore
+ * event!(paro_app, (move |state: &mut ApplicationState, ip: Parsed<IpAddr>| {
+ *     state.server = ip.0;
+ * }))
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parsed<T>(pub T);
+
+impl<T> FromParoValue for Parsed<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    fn from_paro_value(value: Option<String>) -> Result<Self, String> {
+        parse::<T>(value).map(Parsed)
+    }
+}
+
+
+/**
+ * Deserializes the value as JSON with serde.
+ *
+ * ```ignore
+ * event!(paro_app, (move |state: &mut ApplicationState, ids: Json<Vec<u32>>| {
+ *     state.selected = ids.0;
+ * }))
+ * ```
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json<T>(pub T);
+
+impl<T: DeserializeOwned> FromParoValue for Json<T> {
+    fn from_paro_value(value: Option<String>) -> Result<Self, String> {
+        match value {
+            None => Err("no value".to_owned()),
+            Some(value) => serde_json::from_str::<T>(&value)
+                .map(Json)
+                .map_err(|error| format!("could not deserialize '{}': {}", value, error)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    fn value(value: &str) -> Option<String> {
+        Some(value.to_owned())
+    }
+
+    #[test]
+    fn parses_bools() {
+        assert_eq!(bool::from_paro_value(value("true")), Ok(true));
+        assert_eq!(bool::from_paro_value(value("false")), Ok(false));
+        assert!(bool::from_paro_value(value("on")).is_err());
+        assert!(bool::from_paro_value(None).is_err());
+    }
+
+    #[test]
+    fn parses_numbers() {
+        assert_eq!(u64::from_paro_value(value("42")), Ok(42));
+        assert_eq!(i32::from_paro_value(value(" -7 ")), Ok(-7));
+        assert_eq!(f64::from_paro_value(value("1.5")), Ok(1.5));
+        assert!(u8::from_paro_value(value("256")).is_err());
+        assert!(u64::from_paro_value(value("-1")).is_err());
+        assert!(u64::from_paro_value(value("abc")).is_err());
+        assert!(u64::from_paro_value(value("")).is_err());
+    }
+
+    #[test]
+    fn empty_string_is_not_an_error() {
+        assert_eq!(String::from_paro_value(None), Ok("".to_owned()));
+        assert_eq!(String::from_paro_value(value(" a ")), Ok(" a ".to_owned()));
+    }
+
+    #[test]
+    fn option_is_none_without_value() {
+        assert_eq!(Option::<u64>::from_paro_value(None), Ok(None));
+        assert_eq!(Option::<u64>::from_paro_value(value("3")), Ok(Some(3)));
+        assert!(Option::<u64>::from_paro_value(value("x")).is_err());
+        assert_eq!(Option::<String>::from_paro_value(None), Ok(None));
+    }
+
+    #[test]
+    fn parses_from_str_and_json() {
+        assert_eq!(Parsed::<std::net::Ipv4Addr>::from_paro_value(value("127.0.0.1")), Ok(Parsed([127, 0, 0, 1].into())));
+        assert!(Parsed::<std::net::Ipv4Addr>::from_paro_value(value("localhost")).is_err());
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Point { x: i32, y: i32 }
+        assert_eq!(Json::<Point>::from_paro_value(value(r#"{"x": 1, "y": 2}"#)), Ok(Json(Point { x: 1, y: 2 })));
+        assert!(Json::<Point>::from_paro_value(value("{")).is_err());
+        assert!(Json::<Point>::from_paro_value(None).is_err());
+    }
+}